@@ -43,9 +43,15 @@ mod build {
             .flag("-DHAVE_USLEEP=1")
             .flag("-DSQLITE_OMIT_SHARED_CACHE")
             .flag("-DSQLITE_DEFAULT_MEMSTATUS=0")
-            .flag("-DSQLITE_OMIT_PROGRESS_CALLBACK")
             .flag("-DSQLITE_DEFAULT_WAL_SYNCHRONOUS=1");
 
+        if cfg!(feature = "preupdate_hook") {
+            compiler.flag("-DSQLITE_ENABLE_PREUPDATE_HOOK");
+        }
+        if cfg!(feature = "session") {
+            compiler.flag("-DSQLITE_ENABLE_SESSION");
+        }
+
         if cfg!(feature = "sqlcipher") {
             compiler.file("sqlcipher/sqlite3.c")
                 .flag("-DSQLITE_HAS_CODEC")
@@ -236,7 +242,13 @@ mod build {
         pub fn write_to_out_dir(_header: HeaderLocation) {
             let out_dir = env::var("OUT_DIR").unwrap();
             let out_path = Path::new(&out_dir).join("bindgen.rs");
-            let in_path = PREBUILT_BINDGEN_PATHS[PREBUILT_BINDGEN_PATHS.len() - 1];
+            let in_path = if cfg!(feature = "bundled_bindings") {
+                // Use the bindings generated for the bundled SQLite version when linking against
+                // a system SQLite known to be at least as new.
+                "sqlite3/bindgen_bundled_version.rs"
+            } else {
+                PREBUILT_BINDGEN_PATHS[PREBUILT_BINDGEN_PATHS.len() - 1]
+            };
             fs::copy(in_path, out_path).expect("Could not copy bindings to output directory");
         }
     }
@@ -270,9 +282,18 @@ mod build {
             let header: String = header.into();
             let out_dir = env::var("OUT_DIR").unwrap();
             let mut output = Vec::new();
-            bindgen::builder()
+            let mut bindings = bindgen::builder()
                 .header(header.clone())
-                .parse_callbacks(Box::new(SqliteTypeChooser))
+                .parse_callbacks(Box::new(SqliteTypeChooser));
+
+            if cfg!(feature = "preupdate_hook") {
+                bindings = bindings.clang_arg("-DSQLITE_ENABLE_PREUPDATE_HOOK");
+            }
+            if cfg!(feature = "session") {
+                bindings = bindings.clang_arg("-DSQLITE_ENABLE_SESSION");
+            }
+
+            bindings
                 .generate()
                 .expect(&format!("could not run bindgen on header {}", header))
                 .write(Box::new(&mut output))
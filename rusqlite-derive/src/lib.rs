@@ -0,0 +1,353 @@
+//! Derive macros for the `FromRow`, `ToSql` and `FromSql` traits of
+//! [rusqlite](https://crates.io/crates/rusqlite).
+//!
+//! `#[derive(FromRow)]` builds a struct with named fields from a result row,
+//! reading each field from the column with the same name:
+//!
+//! ```rust,ignore
+//! #[macro_use]
+//! extern crate rusqlite_derive;
+//!
+//! #[derive(FromRow)]
+//! struct Person {
+//!     id: i64,
+//!     // read from the `full_name` column
+//!     #[rusqlite(rename = "full_name")]
+//!     name: String,
+//!     // `Default::default()` if the query has no `age` column
+//!     #[rusqlite(default)]
+//!     age: u32,
+//!     // built from the same row with `Address::from_row`
+//!     #[rusqlite(flatten)]
+//!     address: Address,
+//! }
+//! ```
+//!
+//! `#[derive(ToSql, FromSql)]` supports:
+//!
+//! * newtypes, i.e. structs with a single field, stored like their field.
+//! * C-like enums, stored as the integer value of their discriminant, or as
+//!   the name of their variant with `#[rusqlite(storage = "text")]` on the
+//!   enum. A variant can be stored under another name with
+//!   `#[rusqlite(rename = "...")]`.
+//!
+//! ```rust,ignore
+//! #[derive(ToSql, FromSql)]
+//! struct UserId(i64);
+//!
+//! #[derive(ToSql, FromSql)]
+//! #[rusqlite(storage = "text")]
+//! enum Status {
+//!     Active,
+//!     #[rusqlite(rename = "disabled")]
+//!     Inactive,
+//! }
+//! ```
+
+extern crate proc_macro;
+extern crate syn;
+#[macro_use]
+extern crate quote;
+
+use proc_macro::TokenStream;
+use syn::{Body, DeriveInput, Field, Ident, Lit, MetaItem, NestedMetaItem, Variant, VariantData};
+use quote::Tokens;
+
+#[proc_macro_derive(FromRow, attributes(rusqlite))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    expand(input, impl_from_row)
+}
+
+#[proc_macro_derive(ToSql, attributes(rusqlite))]
+pub fn derive_to_sql(input: TokenStream) -> TokenStream {
+    expand(input, impl_to_sql)
+}
+
+#[proc_macro_derive(FromSql, attributes(rusqlite))]
+pub fn derive_from_sql(input: TokenStream) -> TokenStream {
+    expand(input, impl_from_sql)
+}
+
+fn expand(input: TokenStream, f: fn(&DeriveInput) -> Tokens) -> TokenStream {
+    let ast = syn::parse_derive_input(&input.to_string()).unwrap();
+    f(&ast).parse().unwrap()
+}
+
+/// Options given by `#[rusqlite(...)]` attributes.
+#[derive(Default)]
+struct Options {
+    rename: Option<String>,
+    storage: Option<String>,
+    default: bool,
+    flatten: bool,
+}
+
+fn options(attrs: &[syn::Attribute], allowed: &[&str]) -> Options {
+    let mut options = Options::default();
+    for attr in attrs {
+        let items = match attr.value {
+            MetaItem::List(ref name, ref items) if name == "rusqlite" => items,
+            _ => continue,
+        };
+        for item in items {
+            match *item {
+                NestedMetaItem::MetaItem(ref meta) if allowed.contains(&meta.name()) => {
+                    match *meta {
+                        MetaItem::NameValue(ref name, Lit::Str(ref value, _)) => {
+                            if name == "rename" {
+                                options.rename = Some(value.clone());
+                            } else if name == "storage" {
+                                options.storage = Some(value.clone());
+                            } else {
+                                panic!("#[rusqlite({})] doesn't take a value", name);
+                            }
+                        }
+                        MetaItem::Word(ref name) => {
+                            if name == "default" {
+                                options.default = true;
+                            } else if name == "flatten" {
+                                options.flatten = true;
+                            } else {
+                                panic!("#[rusqlite({})] requires a string value", name);
+                            }
+                        }
+                        _ => panic!("malformed #[rusqlite({})] attribute", meta.name()),
+                    }
+                }
+                NestedMetaItem::MetaItem(ref meta) => {
+                    panic!("unsupported #[rusqlite({})] attribute here", meta.name())
+                }
+                NestedMetaItem::Literal(_) => panic!("malformed #[rusqlite(...)] attribute"),
+            }
+        }
+    }
+    options
+}
+
+fn impl_from_row(ast: &DeriveInput) -> Tokens {
+    let name = &ast.ident;
+    let fields = match ast.body {
+        Body::Struct(VariantData::Struct(ref fields)) => fields,
+        _ => panic!("#[derive(FromRow)] is only supported for structs with named fields"),
+    };
+    options(&ast.attrs, &[]);
+
+    let values = fields.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let options = options(&field.attrs, &["rename", "default", "flatten"]);
+        let value = if options.flatten {
+            if options.rename.is_some() {
+                panic!("field `{}` cannot be both renamed and flattened", ident);
+            }
+            quote!(<#ty as ::rusqlite::FromRow>::from_row(row))
+        } else {
+            let column = options.rename.unwrap_or_else(|| ident.to_string());
+            quote!(row.get_checked::<_, #ty>(#column))
+        };
+        if options.default {
+            quote! {
+                #ident: match #value {
+                    Ok(value) => value,
+                    Err(::rusqlite::Error::InvalidColumnName(_)) => {
+                        ::std::default::Default::default()
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        } else {
+            quote! {
+                #ident: match #value {
+                    Ok(value) => value,
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::rusqlite::FromRow for #name #ty_generics #where_clause {
+            fn from_row(row: &::rusqlite::Row) -> ::rusqlite::Result<Self> {
+                Ok(#name {
+                    #(#values),*
+                })
+            }
+        }
+    }
+}
+
+/// Storage of a C-like enum.
+#[derive(Clone, Copy)]
+enum Storage {
+    Integer,
+    Text,
+}
+
+fn enum_storage(ast: &DeriveInput, variants: &[Variant]) -> Storage {
+    if variants.is_empty() {
+        panic!("enum `{}` has no variant", ast.ident);
+    }
+    if variants.iter().any(|v| v.data != VariantData::Unit) {
+        panic!("enum `{}` has variants with fields, only C-like enums are supported",
+               ast.ident);
+    }
+    match options(&ast.attrs, &["storage"]).storage {
+        None => Storage::Integer,
+        Some(ref s) if s == "integer" => Storage::Integer,
+        Some(ref s) if s == "text" => Storage::Text,
+        Some(s) => panic!("unknown storage `{}`, expected \"integer\" or \"text\"", s),
+    }
+}
+
+fn variant_name(variant: &Variant) -> String {
+    options(&variant.attrs, &["rename"])
+        .rename
+        .unwrap_or_else(|| variant.ident.to_string())
+}
+
+// Single field of a newtype, and how to access it.
+fn newtype_field(ast: &DeriveInput) -> Option<(&Field, Ident)> {
+    match ast.body {
+        Body::Struct(VariantData::Tuple(ref fields)) if fields.len() == 1 => {
+            Some((&fields[0], Ident::new("0")))
+        }
+        Body::Struct(VariantData::Struct(ref fields)) if fields.len() == 1 => {
+            Some((&fields[0], fields[0].ident.clone().unwrap()))
+        }
+        Body::Struct(_) => {
+            panic!("#[derive(ToSql, FromSql)] on a struct requires exactly one field")
+        }
+        Body::Enum(_) => None,
+    }
+}
+
+// Generics of the newtype, with the field type bounded by `bound`.
+fn bounded_generics(ast: &DeriveInput, field: &Field, bound: &str) -> syn::Generics {
+    let mut generics = ast.generics.clone();
+    if !generics.ty_params.is_empty() {
+        let ty = &field.ty;
+        let clause = syn::parse_where_clause(&format!("where {}: {}", quote!(#ty), bound))
+            .unwrap();
+        generics.where_clause.predicates.extend(clause.predicates);
+    }
+    generics
+}
+
+fn impl_to_sql(ast: &DeriveInput) -> Tokens {
+    let name = &ast.ident;
+    let body = match ast.body {
+        Body::Enum(ref variants) => {
+            let storage = enum_storage(ast, variants);
+            let arms = variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                match storage {
+                    Storage::Integer => {
+                        quote! {
+                            #name::#ident => {
+                                ::rusqlite::types::ToSqlOutput::from(#name::#ident as i64)
+                            }
+                        }
+                    }
+                    Storage::Text => {
+                        let text = variant_name(variant);
+                        quote! {
+                            #name::#ident => ::rusqlite::types::ToSqlOutput::from(#text)
+                        }
+                    }
+                }
+            });
+            quote! {
+                Ok(match *self {
+                    #(#arms),*
+                })
+            }
+        }
+        Body::Struct(_) => {
+            let (_, access) = newtype_field(ast).unwrap();
+            quote!(::rusqlite::types::ToSql::to_sql(&self.#access))
+        }
+    };
+
+    let generics = match newtype_field(ast) {
+        Some((field, _)) => bounded_generics(ast, field, "::rusqlite::types::ToSql"),
+        None => ast.generics.clone(),
+    };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::rusqlite::types::ToSql for #name #ty_generics #where_clause {
+            fn to_sql(&self) -> ::rusqlite::Result<::rusqlite::types::ToSqlOutput> {
+                #body
+            }
+        }
+    }
+}
+
+fn impl_from_sql(ast: &DeriveInput) -> Tokens {
+    let name = &ast.ident;
+    let body = match ast.body {
+        Body::Enum(ref variants) => {
+            match enum_storage(ast, variants) {
+                Storage::Integer => {
+                    let checks = variants.iter().map(|variant| {
+                        let ident = &variant.ident;
+                        quote! {
+                            if i == #name::#ident as i64 {
+                                return Ok(#name::#ident);
+                            }
+                        }
+                    });
+                    quote! {
+                        let i = match value.as_i64() {
+                            Ok(i) => i,
+                            Err(err) => return Err(err),
+                        };
+                        #(#checks)*
+                        Err(::rusqlite::types::FromSqlError::OutOfRange(i))
+                    }
+                }
+                Storage::Text => {
+                    let arms = variants.iter().map(|variant| {
+                        let ident = &variant.ident;
+                        let text = variant_name(variant);
+                        quote!(Ok(#text) => Ok(#name::#ident))
+                    });
+                    let type_name = name.to_string();
+                    quote! {
+                        match value.as_str() {
+                            #(#arms,)*
+                            Ok(s) => {
+                                Err(::rusqlite::types::FromSqlError::Other(
+                                    format!("unknown {} variant `{}`", #type_name, s).into()))
+                            }
+                            Err(err) => Err(err),
+                        }
+                    }
+                }
+            }
+        }
+        Body::Struct(VariantData::Tuple(_)) => {
+            quote!(::rusqlite::types::FromSql::column_result(value).map(#name))
+        }
+        Body::Struct(_) => {
+            let (_, ident) = newtype_field(ast).unwrap();
+            quote! {
+                ::rusqlite::types::FromSql::column_result(value).map(|v| #name { #ident: v })
+            }
+        }
+    };
+
+    let generics = match newtype_field(ast) {
+        Some((field, _)) => bounded_generics(ast, field, "::rusqlite::types::FromSql"),
+        None => ast.generics.clone(),
+    };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::rusqlite::types::FromSql for #name #ty_generics #where_clause {
+            fn column_result(value: ::rusqlite::types::ValueRef)
+                             -> ::rusqlite::types::FromSqlResult<Self> {
+                #body
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+extern crate rusqlite;
+#[macro_use]
+extern crate rusqlite_derive;
+
+use rusqlite::{Connection, Error, FromRow};
+use rusqlite::types::{FromSql, ToSql, ToSqlOutput, Value, ValueRef};
+
+#[derive(Debug, PartialEq, ToSql, FromSql)]
+struct UserId(i64);
+
+#[derive(Debug, PartialEq, ToSql, FromSql)]
+struct Email {
+    address: String,
+}
+
+#[derive(Debug, PartialEq, ToSql, FromSql)]
+struct Wrapper<T>(T);
+
+#[derive(Debug, PartialEq, ToSql, FromSql)]
+enum Level {
+    Low = 1,
+    High = 10,
+}
+
+#[derive(Debug, PartialEq, ToSql, FromSql)]
+#[rusqlite(storage = "text")]
+enum Status {
+    Active,
+    #[rusqlite(rename = "disabled")]
+    Inactive,
+}
+
+#[derive(Debug, Default, PartialEq, FromRow)]
+struct Address {
+    city: String,
+    #[rusqlite(rename = "zip_code")]
+    zip: Option<String>,
+}
+
+#[derive(Debug, PartialEq, FromRow)]
+struct User {
+    id: UserId,
+    #[rusqlite(rename = "full_name")]
+    name: String,
+    status: Status,
+    #[rusqlite(default)]
+    level: Option<Level>,
+    #[rusqlite(flatten)]
+    address: Address,
+}
+
+fn checked_memory_handle() -> Connection {
+    let db = Connection::open_in_memory().unwrap();
+    db.execute_batch("CREATE TABLE user (id INTEGER PRIMARY KEY, full_name TEXT, status TEXT,
+                                         level INTEGER, city TEXT, zip_code TEXT);
+                      INSERT INTO user VALUES (1, 'Alice', 'Active', 10, 'Paris', '75001');
+                      INSERT INTO user VALUES (2, 'Bob', 'disabled', NULL, 'Lyon', NULL);")
+        .unwrap();
+    db
+}
+
+#[test]
+fn test_from_row() {
+    let db = checked_memory_handle();
+    let mut stmt = db.prepare("SELECT * FROM user ORDER BY id").unwrap();
+    let users: Vec<User> = stmt.query_and_then(&[], User::from_row)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(vec![User {
+                        id: UserId(1),
+                        name: "Alice".to_owned(),
+                        status: Status::Active,
+                        level: Some(Level::High),
+                        address: Address {
+                            city: "Paris".to_owned(),
+                            zip: Some("75001".to_owned()),
+                        },
+                    },
+                    User {
+                        id: UserId(2),
+                        name: "Bob".to_owned(),
+                        status: Status::Inactive,
+                        level: None,
+                        address: Address {
+                            city: "Lyon".to_owned(),
+                            zip: None,
+                        },
+                    }],
+               users);
+}
+
+#[test]
+fn test_from_row_missing_column() {
+    let db = checked_memory_handle();
+    let user = db.query_row("SELECT id, full_name, status, city, zip_code FROM user WHERE id = 1",
+                   &[],
+                   User::from_row)
+        .unwrap()
+        .unwrap();
+    assert_eq!(None, user.level);
+
+    match db.query_row("SELECT id, full_name, status FROM user", &[], User::from_row)
+              .unwrap() {
+        Err(Error::InvalidColumnName(name)) => assert_eq!("city", name),
+        r => panic!("Unexpected result {:?}", r),
+    }
+}
+
+#[test]
+fn test_newtypes() {
+    assert_eq!(ToSqlOutput::from(42), UserId(42).to_sql().unwrap());
+    assert_eq!(ToSqlOutput::from("a@example.com"),
+               Email { address: "a@example.com".to_owned() }.to_sql().unwrap());
+    assert_eq!(ToSqlOutput::from(1.5), Wrapper(1.5).to_sql().unwrap());
+
+    assert_eq!(UserId(42), UserId::column_result(ValueRef::Integer(42)).unwrap());
+    assert_eq!(Email { address: "a@example.com".to_owned() },
+               Email::column_result(ValueRef::Text("a@example.com")).unwrap());
+    assert_eq!(Wrapper("x".to_owned()),
+               Wrapper::<String>::column_result(ValueRef::Text("x")).unwrap());
+    assert!(UserId::column_result(ValueRef::Text("42")).is_err());
+}
+
+#[test]
+fn test_enums() {
+    assert_eq!(ToSqlOutput::from(10), Level::High.to_sql().unwrap());
+    assert_eq!(Level::Low, Level::column_result(ValueRef::Integer(1)).unwrap());
+    assert!(Level::column_result(ValueRef::Integer(2)).is_err());
+
+    assert_eq!(ToSqlOutput::from("disabled"), Status::Inactive.to_sql().unwrap());
+    assert_eq!(Status::Active, Status::column_result(ValueRef::Text("Active")).unwrap());
+    assert!(Status::column_result(ValueRef::Text("Inactive")).is_err());
+    assert!(Status::column_result(ValueRef::Integer(0)).is_err());
+}
+
+#[test]
+fn test_round_trip() {
+    let db = checked_memory_handle();
+    db.execute("UPDATE user SET status = ?, level = ? WHERE id = ?",
+                 &[&Status::Inactive as &ToSql, &Level::Low, &UserId(1)])
+        .unwrap();
+    let (status, level): (Status, Level) =
+        db.query_row("SELECT status, level FROM user WHERE id = 1",
+                       &[],
+                       |r| (r.get(0), r.get(1)))
+            .unwrap();
+    assert_eq!((Status::Inactive, Level::Low), (status, level));
+    let value: Value = db.query_row("SELECT status FROM user WHERE id = 1", &[], |r| r.get(0))
+        .unwrap();
+    assert_eq!(Value::Text("disabled".to_owned()), value);
+}
@@ -0,0 +1,195 @@
+//! Busy handler (when the database is locked)
+
+use std::os::raw::{c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::time::Duration;
+
+use ffi;
+use {Connection, InnerConnection, Result};
+
+impl Connection {
+    /// Set a busy handler that sleeps for a specified amount of time when a
+    /// table is locked. The handler will sleep multiple times until at least
+    /// `timeout` has elapsed.
+    ///
+    /// Calling this method replaces any busy handler previously installed
+    /// with `busy_handler`. A `timeout` of zero turns off all busy handlers.
+    ///
+    /// Newly opened connections use a timeout of 5 seconds.
+    pub fn busy_timeout(&self, timeout: Duration) -> Result<()> {
+        let ms = timeout.as_secs()
+            .checked_mul(1000)
+            .and_then(|t| t.checked_add(u64::from(timeout.subsec_nanos() / 1_000_000)))
+            .map_or(c_int::max_value(), |t| if t > c_int::max_value() as u64 {
+                c_int::max_value()
+            } else {
+                t as c_int
+            });
+        self.db.borrow_mut().busy_timeout(ms)
+    }
+
+    /// Register a callback to handle `SQLITE_BUSY` errors.
+    ///
+    /// If the busy callback is `None`, then `SQLITE_BUSY` is returned
+    /// immediately upon encountering the lock. The argument to the busy
+    /// handler callback is the number of times that the busy handler has
+    /// been invoked previously for the same locking event. If the busy
+    /// callback returns `false`, then no additional attempts are made to
+    /// access the database and `SQLITE_BUSY` is returned to the application.
+    /// If the callback returns `true`, then another attempt is made to access
+    /// the database and the cycle repeats.
+    ///
+    /// There can only be a single busy handler defined for each database
+    /// connection. Setting a new busy handler (or calling `busy_timeout`)
+    /// clears any previously set handler. The callback is kept alive until it
+    /// is replaced or the connection is closed.
+    pub fn busy_handler<F>(&self, callback: Option<F>) -> Result<()>
+        where F: FnMut(i32) -> bool + Send + 'static
+    {
+        self.db.borrow_mut().busy_handler(callback)
+    }
+}
+
+impl InnerConnection {
+    fn busy_timeout(&mut self, timeout: c_int) -> Result<()> {
+        let r = unsafe { ffi::sqlite3_busy_timeout(self.db, timeout) };
+        try!(self.decode_result(r));
+        // `sqlite3_busy_timeout` replaces any user-installed handler.
+        self.busy_handler = None;
+        Ok(())
+    }
+
+    fn busy_handler<F>(&mut self, callback: Option<F>) -> Result<()>
+        where F: FnMut(i32) -> bool + Send + 'static
+    {
+        unsafe extern "C" fn call_boxed_closure(p_arg: *mut c_void, count: c_int) -> c_int {
+            let boxed_handler = p_arg as *mut Box<FnMut(i32) -> bool + Send>;
+            let r = catch_unwind(AssertUnwindSafe(|| (*boxed_handler)(count)));
+            if let Ok(true) = r { 1 } else { 0 }
+        }
+
+        let boxed_handler = callback.map(|f| {
+            let handler: Box<FnMut(i32) -> bool + Send> = Box::new(f);
+            Box::new(handler)
+        });
+        let r = match boxed_handler {
+            Some(ref handler) => {
+                let p_arg = &**handler as *const Box<FnMut(i32) -> bool + Send> as *mut c_void;
+                unsafe { ffi::sqlite3_busy_handler(self.db, Some(call_boxed_closure), p_arg) }
+            }
+            None => unsafe { ffi::sqlite3_busy_handler(self.db, None, ptr::null_mut()) },
+        };
+        try!(self.decode_result(r));
+        // SQLite no longer refers to the previous handler, so it's safe to drop it now.
+        self.busy_handler = boxed_handler;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate tempdir;
+    use self::tempdir::TempDir;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, ATOMIC_BOOL_INIT};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use {Connection, Error, ErrorCode, TransactionBehavior};
+
+    #[test]
+    fn test_busy_timeout() {
+        let temp_dir = TempDir::new("test_busy_timeout").unwrap();
+        let path = temp_dir.path().join("test.db3");
+
+        let mut db1 = Connection::open(&path).unwrap();
+        let tx1 = db1.transaction_with_behavior(TransactionBehavior::Exclusive).unwrap();
+        let db2 = Connection::open(&path).unwrap();
+        db2.busy_timeout(Duration::from_millis(50)).unwrap();
+        let r = db2.query_row("PRAGMA schema_version", &[], |row| row.get::<_, i32>(0));
+        match r.unwrap_err() {
+            Error::SqliteFailure(err, _) => assert_eq!(err.code, ErrorCode::DatabaseBusy),
+            err => panic!("Unexpected error {}", err),
+        }
+        tx1.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_busy_handler() {
+        static CALLED: AtomicBool = ATOMIC_BOOL_INIT;
+        fn busy_handler(_: i32) -> bool {
+            CALLED.store(true, Ordering::Relaxed);
+            false
+        }
+
+        let temp_dir = TempDir::new("test_busy_handler").unwrap();
+        let path = temp_dir.path().join("test.db3");
+
+        let mut db1 = Connection::open(&path).unwrap();
+        let tx1 = db1.transaction_with_behavior(TransactionBehavior::Exclusive).unwrap();
+        let db2 = Connection::open(&path).unwrap();
+        db2.busy_handler(Some(busy_handler)).unwrap();
+        let r = db2.query_row("PRAGMA schema_version", &[], |row| row.get::<_, i32>(0));
+        assert!(r.is_err());
+        assert!(CALLED.load(Ordering::Relaxed));
+        tx1.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_busy_handler_closure() {
+        let temp_dir = TempDir::new("test_busy_handler_closure").unwrap();
+        let path = temp_dir.path().join("test.db3");
+
+        let mut db1 = Connection::open(&path).unwrap();
+        let tx1 = db1.transaction_with_behavior(TransactionBehavior::Exclusive).unwrap();
+        let db2 = Connection::open(&path).unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = calls.clone();
+        db2.busy_handler(Some(move |count: i32| {
+                                  handler_calls.fetch_add(1, Ordering::SeqCst);
+                                  count < 3
+                              }))
+            .unwrap();
+        let r = db2.query_row("PRAGMA schema_version", &[], |row| row.get::<_, i32>(0));
+        match r.unwrap_err() {
+            Error::SqliteFailure(err, _) => assert_eq!(err.code, ErrorCode::DatabaseBusy),
+            err => panic!("Unexpected error {}", err),
+        }
+        assert_eq!(4, calls.load(Ordering::SeqCst));
+
+        // Clearing the handler drops the closure (and its reference to `calls`).
+        db2.busy_handler(None::<fn(i32) -> bool>).unwrap();
+        assert_eq!(1, Arc::strong_count(&calls));
+        let r = db2.query_row("PRAGMA schema_version", &[], |row| row.get::<_, i32>(0));
+        assert!(r.is_err());
+        assert_eq!(4, calls.load(Ordering::SeqCst));
+        tx1.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_busy_timeout_replaces_handler() {
+        let db = Connection::open_in_memory().unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = calls.clone();
+        db.busy_handler(Some(move |_| {
+                                 handler_calls.fetch_add(1, Ordering::SeqCst);
+                                 false
+                             }))
+            .unwrap();
+        assert_eq!(2, Arc::strong_count(&calls));
+        db.busy_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(1, Arc::strong_count(&calls));
+
+        // Dropping the connection drops the handler.
+        let handler_calls = calls.clone();
+        db.busy_handler(Some(move |_| {
+                                 handler_calls.fetch_add(1, Ordering::SeqCst);
+                                 false
+                             }))
+            .unwrap();
+        assert_eq!(2, Arc::strong_count(&calls));
+        drop(db);
+        assert_eq!(1, Arc::strong_count(&calls));
+    }
+}
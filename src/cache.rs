@@ -253,7 +253,7 @@ mod test {
         {
             let mut stmt = db.prepare_cached(sql).unwrap();
             assert_eq!(1i32,
-                       stmt.query_map::<i32, _>(&[], |r| r.get(0))
+                       stmt.query_map::<i32, _, _>(&[], |r| r.get(0))
                            .unwrap()
                            .next()
                            .unwrap()
@@ -0,0 +1,244 @@
+//! Add, remove, or modify a collation
+use std::cmp::Ordering;
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::process;
+use std::ptr;
+use std::slice;
+
+use ffi;
+use {str_to_cstring, Connection, InnerConnection, Result};
+
+unsafe extern "C" fn free_boxed_value<T>(p: *mut c_void) {
+    let _: Box<T> = Box::from_raw(p as *mut T);
+}
+
+impl Connection {
+    /// Add or modify a collation.
+    ///
+    /// The collation can then be used in `ORDER BY` clauses and in column
+    /// definitions (`COLLATE name`). The comparison must be consistent: if it
+    /// panics the process is aborted, since SQLite cannot be told the
+    /// comparison failed and an inconsistent ordering could corrupt indexes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use rusqlite::{Connection, Result};
+    /// fn nocase_ascii(conn: &Connection) -> Result<()> {
+    ///     conn.create_collation("nocase_ascii", |a, b| {
+    ///         a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase())
+    ///     })
+    /// }
+    /// ```
+    pub fn create_collation<C>(&self, collation_name: &str, x_compare: C) -> Result<()>
+        where C: Fn(&str, &str) -> Ordering + Send + 'static
+    {
+        self.db
+            .borrow_mut()
+            .create_collation(collation_name, x_compare)
+    }
+
+    /// Collation needed callback.
+    ///
+    /// `x_coll_needed` is invoked whenever SQLite needs a collation sequence
+    /// that hasn't been registered yet, and can call `create_collation` on the
+    /// `Connection` it receives to register it lazily. That `Connection` only
+    /// borrows the underlying database handle and is invalid once the callback
+    /// returns.
+    pub fn collation_needed(&self,
+                            x_coll_needed: fn(&Connection, &str) -> Result<()>)
+                            -> Result<()> {
+        self.db.borrow_mut().collation_needed(x_coll_needed)
+    }
+
+    /// Remove collation.
+    pub fn remove_collation(&self, collation_name: &str) -> Result<()> {
+        self.db.borrow_mut().remove_collation(collation_name)
+    }
+}
+
+impl InnerConnection {
+    fn create_collation<C>(&mut self, collation_name: &str, x_compare: C) -> Result<()>
+        where C: Fn(&str, &str) -> Ordering + Send + 'static
+    {
+        unsafe extern "C" fn call_boxed_closure<C>(arg1: *mut c_void,
+                                                   arg2: c_int,
+                                                   arg3: *const c_void,
+                                                   arg4: c_int,
+                                                   arg5: *const c_void)
+                                                   -> c_int
+            where C: Fn(&str, &str) -> Ordering
+        {
+            let boxed_f: *mut C = arg1 as *mut C;
+            assert!(!boxed_f.is_null(), "Internal error - null function pointer");
+            let s1 = slice::from_raw_parts(arg3 as *const u8, arg2 as usize);
+            let s2 = slice::from_raw_parts(arg5 as *const u8, arg4 as usize);
+            let r = catch_unwind(AssertUnwindSafe(|| {
+                let s1 = String::from_utf8_lossy(s1);
+                let s2 = String::from_utf8_lossy(s2);
+                (*boxed_f)(&s1, &s2)
+            }));
+            match r {
+                Ok(Ordering::Less) => -1,
+                Ok(Ordering::Equal) => 0,
+                Ok(Ordering::Greater) => 1,
+                Err(_) => process::abort(),
+            }
+        }
+
+        let c_name = try!(str_to_cstring(collation_name));
+        let boxed_f: *mut C = Box::into_raw(Box::new(x_compare));
+        let flags = ffi::SQLITE_UTF8;
+        let r = unsafe {
+            ffi::sqlite3_create_collation_v2(self.db(),
+                                             c_name.as_ptr(),
+                                             flags,
+                                             boxed_f as *mut c_void,
+                                             Some(call_boxed_closure::<C>),
+                                             Some(free_boxed_value::<C>))
+        };
+        if r != ffi::SQLITE_OK {
+            // SQLite only takes ownership of the closure on success.
+            unsafe { free_boxed_value::<C>(boxed_f as *mut c_void) };
+        }
+        self.decode_result(r)
+    }
+
+    fn collation_needed(&mut self,
+                        x_coll_needed: fn(&Connection, &str) -> Result<()>)
+                        -> Result<()> {
+        unsafe extern "C" fn collation_needed_callback(arg1: *mut c_void,
+                                                       arg2: *mut ffi::sqlite3,
+                                                       _e_text_rep: c_int,
+                                                       arg3: *const c_char) {
+            let callback: fn(&Connection, &str) -> Result<()> = mem::transmute(arg1);
+            let conn = Connection::from_handle(arg2);
+            let collation_name = String::from_utf8_lossy(CStr::from_ptr(arg3).to_bytes());
+            // If the callback fails to register the collation, SQLite reports
+            // the missing collation sequence itself.
+            let _ = catch_unwind(AssertUnwindSafe(|| callback(&conn, &collation_name)));
+        }
+
+        let r = unsafe {
+            ffi::sqlite3_collation_needed(self.db(),
+                                          x_coll_needed as *mut c_void,
+                                          Some(collation_needed_callback))
+        };
+        self.decode_result(r)
+    }
+
+    fn remove_collation(&mut self, collation_name: &str) -> Result<()> {
+        let c_name = try!(str_to_cstring(collation_name));
+        let r = unsafe {
+            ffi::sqlite3_create_collation_v2(self.db(),
+                                             c_name.as_ptr(),
+                                             ffi::SQLITE_UTF8,
+                                             ptr::null_mut(),
+                                             None,
+                                             None)
+        };
+        self.decode_result(r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+    use {Connection, Error, Result};
+
+    fn unicase_compare(s1: &str, s2: &str) -> Ordering {
+        s1.to_lowercase().cmp(&s2.to_lowercase())
+    }
+
+    #[test]
+    fn test_unicase() {
+        let db = Connection::open_in_memory().unwrap();
+
+        db.create_collation("unicase", unicase_compare).unwrap();
+
+        collate(db);
+    }
+
+    fn collate(db: Connection) {
+        db.execute_batch("CREATE TABLE foo (bar);
+                          INSERT INTO foo (bar) VALUES ('B');
+                          INSERT INTO foo (bar) VALUES ('a');
+                          INSERT INTO foo (bar) VALUES ('C');
+                          INSERT INTO foo (bar) VALUES ('A');")
+            .unwrap();
+        let mut stmt = db.prepare("SELECT bar FROM foo ORDER BY bar COLLATE unicase, bar")
+            .unwrap();
+        let rows: Vec<String> = stmt.query_map(&[], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows, vec!["A", "a", "B", "C"]);
+        let count: i64 = db.query_row("SELECT COUNT(*) FROM foo WHERE bar = 'a' COLLATE unicase",
+                                      &[],
+                                      |row| row.get(0))
+            .unwrap();
+        assert_eq!(2, count);
+    }
+
+    #[test]
+    fn test_natural_order() {
+        fn natural(s1: &str, s2: &str) -> Ordering {
+            let key = |s: &str| {
+                let digits: String = s.chars().filter(|c| c.is_digit(10)).collect();
+                (digits.parse::<u64>().unwrap_or(0), s.to_owned())
+            };
+            key(s1).cmp(&key(s2))
+        }
+
+        let db = Connection::open_in_memory().unwrap();
+        db.create_collation("natural_order", natural).unwrap();
+        db.execute_batch("CREATE TABLE foo (bar TEXT);
+                          INSERT INTO foo VALUES ('file10');
+                          INSERT INTO foo VALUES ('file9');
+                          INSERT INTO foo VALUES ('file100');")
+            .unwrap();
+        let mut stmt = db.prepare("SELECT bar FROM foo ORDER BY bar COLLATE natural_order")
+            .unwrap();
+        let rows: Vec<String> = stmt.query_map(&[], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows, vec!["file9", "file10", "file100"]);
+    }
+
+    #[test]
+    fn test_remove_collation() {
+        let db = Connection::open_in_memory().unwrap();
+        db.create_collation("unicase", unicase_compare).unwrap();
+        db.remove_collation("unicase").unwrap();
+        db.execute_batch("CREATE TABLE foo (bar)").unwrap();
+        assert!(db.prepare("SELECT bar FROM foo ORDER BY bar COLLATE unicase").is_err());
+    }
+
+    #[test]
+    fn test_invalid_collation_name() {
+        let db = Connection::open_in_memory().unwrap();
+        match db.create_collation("uni\0case", unicase_compare) {
+            Err(Error::NulError(_)) => {}
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+
+    fn collation_needed(db: &Connection, collation_name: &str) -> Result<()> {
+        if "unicase" == collation_name {
+            db.create_collation(collation_name, unicase_compare)
+        } else {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_collation_needed() {
+        let db = Connection::open_in_memory().unwrap();
+        db.collation_needed(collation_needed).unwrap();
+        collate(db);
+    }
+}
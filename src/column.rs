@@ -0,0 +1,215 @@
+use std::ffi::CStr;
+#[cfg(feature = "column_metadata")]
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+#[cfg(feature = "column_metadata")]
+use std::ptr;
+use std::str;
+
+#[cfg(feature = "column_metadata")]
+use ffi;
+#[cfg(feature = "column_metadata")]
+use error::error_from_handle;
+#[cfg(feature = "column_metadata")]
+use {Connection, DatabaseName, Result};
+use raw_statement::RawStatement;
+
+fn to_str(s: Option<&CStr>) -> Option<&str> {
+    s.map(|s| str::from_utf8(s.to_bytes()).unwrap())
+}
+
+/// Information about a column of the result set of a prepared statement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column<'stmt> {
+    name: &'stmt str,
+    decl_type: Option<&'stmt str>,
+    #[cfg(feature = "column_metadata")]
+    database_name: Option<&'stmt str>,
+    #[cfg(feature = "column_metadata")]
+    table_name: Option<&'stmt str>,
+    #[cfg(feature = "column_metadata")]
+    origin_name: Option<&'stmt str>,
+}
+
+impl<'stmt> Column<'stmt> {
+    pub(crate) fn new(stmt: &'stmt RawStatement, i: c_int) -> Column<'stmt> {
+        Column {
+            name: str::from_utf8(stmt.column_name(i).to_bytes()).unwrap(),
+            decl_type: to_str(stmt.column_decltype(i)),
+            #[cfg(feature = "column_metadata")]
+            database_name: to_str(stmt.column_database_name(i)),
+            #[cfg(feature = "column_metadata")]
+            table_name: to_str(stmt.column_table_name(i)),
+            #[cfg(feature = "column_metadata")]
+            origin_name: to_str(stmt.column_origin_name(i)),
+        }
+    }
+
+    /// The name of the column, as given by its `AS` clause if any.
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// The declared type of the table column the result column comes from, or
+    /// `None` for an expression or a column declared without a type.
+    pub fn decl_type(&self) -> Option<&str> {
+        self.decl_type
+    }
+
+    /// The name of the database ("main", "temp", ...) the result column comes
+    /// from, or `None` for an expression.
+    #[cfg(feature = "column_metadata")]
+    pub fn database_name(&self) -> Option<&str> {
+        self.database_name
+    }
+
+    /// The name of the table the result column comes from, or `None` for an
+    /// expression.
+    #[cfg(feature = "column_metadata")]
+    pub fn table_name(&self) -> Option<&str> {
+        self.table_name
+    }
+
+    /// The name of the table column the result column comes from, or `None`
+    /// for an expression.
+    #[cfg(feature = "column_metadata")]
+    pub fn origin_name(&self) -> Option<&str> {
+        self.origin_name
+    }
+}
+
+/// Metadata about a table column, as returned by `Connection::column_metadata`.
+#[cfg(feature = "column_metadata")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnMetadata {
+    /// The declared type, or `None` if the column was declared without one.
+    pub data_type: Option<String>,
+    /// The name of the default collation sequence.
+    pub collation_sequence: String,
+    /// Whether the column has a `NOT NULL` constraint.
+    pub not_null: bool,
+    /// Whether the column is part of the primary key.
+    pub primary_key: bool,
+    /// Whether the column is an `AUTOINCREMENT` rowid.
+    pub auto_increment: bool,
+}
+
+#[cfg(feature = "column_metadata")]
+impl Connection {
+    /// Get metadata about the column `column_name` of the table `table_name`.
+    /// If `db_name` is `None`, the table is searched for in all the databases
+    /// of the connection, in the same order as for unqualified table names.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if there is no such table or column.
+    pub fn column_metadata(&self,
+                           db_name: Option<DatabaseName>,
+                           table_name: &str,
+                           column_name: &str)
+                           -> Result<ColumnMetadata> {
+        let db_name = match db_name {
+            Some(db_name) => Some(try!(db_name.to_cstring())),
+            None => None,
+        };
+        let table_name = try!(::str_to_cstring(table_name));
+        let column_name = try!(::str_to_cstring(column_name));
+
+        let mut data_type: *const c_char = ptr::null();
+        let mut coll_seq: *const c_char = ptr::null();
+        let mut not_null: c_int = 0;
+        let mut primary_key: c_int = 0;
+        let mut auto_inc: c_int = 0;
+        let c = self.db.borrow();
+        unsafe {
+            let r = ffi::sqlite3_table_column_metadata(c.db(),
+                                                       db_name
+                                                           .as_ref()
+                                                           .map_or(ptr::null(), |s| s.as_ptr()),
+                                                       table_name.as_ptr(),
+                                                       column_name.as_ptr(),
+                                                       &mut data_type,
+                                                       &mut coll_seq,
+                                                       &mut not_null,
+                                                       &mut primary_key,
+                                                       &mut auto_inc);
+            if r != ffi::SQLITE_OK {
+                return Err(error_from_handle(c.db(), r));
+            }
+            let to_string = |p: *const c_char| if p.is_null() {
+                None
+            } else {
+                Some(String::from_utf8_lossy(CStr::from_ptr(p).to_bytes()).into_owned())
+            };
+            Ok(ColumnMetadata {
+                   data_type: to_string(data_type),
+                   collation_sequence: to_string(coll_seq).unwrap_or_else(|| "BINARY".to_owned()),
+                   not_null: not_null != 0,
+                   primary_key: primary_key != 0,
+                   auto_increment: auto_inc != 0,
+               })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use Connection;
+
+    #[test]
+    fn test_columns() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo (id INTEGER PRIMARY KEY, name TEXT, untyped)").unwrap();
+        let stmt = db.prepare("SELECT id, name AS n, untyped, 1 + 1 FROM foo").unwrap();
+        let columns = stmt.columns();
+        assert_eq!(vec!["id", "n", "untyped", "1 + 1"],
+                   columns.iter().map(|c| c.name()).collect::<Vec<_>>());
+        assert_eq!(vec![Some("INTEGER"), Some("TEXT"), None, None],
+                   columns.iter().map(|c| c.decl_type()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[cfg(feature = "column_metadata")]
+    fn test_column_origin() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo (id INTEGER PRIMARY KEY, name TEXT)").unwrap();
+        let stmt = db.prepare("SELECT name AS n, 1 FROM foo").unwrap();
+        let columns = stmt.columns();
+        assert_eq!(Some("main"), columns[0].database_name());
+        assert_eq!(Some("foo"), columns[0].table_name());
+        assert_eq!(Some("name"), columns[0].origin_name());
+        assert_eq!(None, columns[1].table_name());
+        assert_eq!(None, columns[1].origin_name());
+    }
+
+    #[test]
+    #[cfg(feature = "column_metadata")]
+    fn test_column_metadata() {
+        use DatabaseName;
+
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+                          name TEXT NOT NULL COLLATE NOCASE, untyped)")
+            .unwrap();
+
+        let id = db.column_metadata(None, "foo", "id").unwrap();
+        assert_eq!(Some("INTEGER".to_owned()), id.data_type);
+        assert_eq!("BINARY", id.collation_sequence);
+        assert!(!id.not_null);
+        assert!(id.primary_key);
+        assert!(id.auto_increment);
+
+        let name = db.column_metadata(Some(DatabaseName::Main), "foo", "name").unwrap();
+        assert_eq!(Some("TEXT".to_owned()), name.data_type);
+        assert_eq!("NOCASE", name.collation_sequence);
+        assert!(name.not_null);
+        assert!(!name.primary_key);
+        assert!(!name.auto_increment);
+
+        let untyped = db.column_metadata(None, "foo", "untyped").unwrap();
+        assert_eq!(None, untyped.data_type);
+
+        assert!(db.column_metadata(None, "foo", "missing").is_err());
+        assert!(db.column_metadata(Some(DatabaseName::Temp), "foo", "id").is_err());
+    }
+}
@@ -77,6 +77,25 @@ pub enum Error {
 
     /// Error available for the implementors of the `ToSql` trait.
     ToSqlConversionFailure(Box<error::Error + Send + Sync>),
+
+    /// Error returned by `vtab::Values::get` when the filter argument cannot be converted to the
+    /// requested type.
+    #[cfg(feature = "vtab")]
+    InvalidFilterParameterType(usize, Type),
+
+    /// An error case available for implementors of custom modules (e.g., `create_module`).
+    #[cfg(feature = "vtab")]
+    #[allow(dead_code)]
+    ModuleError(String),
+
+    /// Error when a migration cannot be applied or reverted, or when the
+    /// migrated schema doesn't match the expected one (see `migrations`).
+    MigrationError(String),
+
+    /// Error when a row cannot be deserialized, or parameters cannot be serialized, with serde
+    /// (see `serde::from_row` and `serde::to_params_named`).
+    #[cfg(feature = "serde")]
+    SerdeError(String),
 }
 
 impl From<str::Utf8Error> for Error {
@@ -132,6 +151,15 @@ impl fmt::Display for Error {
             #[cfg(feature = "functions")]
             Error::UserFunctionError(ref err) => err.fmt(f),
             Error::ToSqlConversionFailure(ref err) => err.fmt(f),
+            #[cfg(feature = "vtab")]
+            Error::InvalidFilterParameterType(i, ref t) => {
+                write!(f, "Invalid filter parameter type {} at index {}", t, i)
+            }
+            #[cfg(feature = "vtab")]
+            Error::ModuleError(ref desc) => write!(f, "{}", desc),
+            Error::MigrationError(ref desc) => write!(f, "{}", desc),
+            #[cfg(feature = "serde")]
+            Error::SerdeError(ref desc) => write!(f, "{}", desc),
         }
     }
 }
@@ -160,6 +188,13 @@ impl error::Error for Error {
             #[cfg(feature = "functions")]
             Error::UserFunctionError(ref err) => err.description(),
             Error::ToSqlConversionFailure(ref err) => err.description(),
+            #[cfg(feature = "vtab")]
+            Error::InvalidFilterParameterType(_, _) => "invalid filter parameter type",
+            #[cfg(feature = "vtab")]
+            Error::ModuleError(ref desc) => desc,
+            Error::MigrationError(ref desc) => desc,
+            #[cfg(feature = "serde")]
+            Error::SerdeError(ref desc) => desc,
         }
     }
 
@@ -178,7 +213,8 @@ impl error::Error for Error {
             Error::InvalidColumnName(_) |
             Error::InvalidColumnType(_, _) |
             Error::InvalidPath(_) |
-            Error::StatementChangedRows(_) => None,
+            Error::StatementChangedRows(_) |
+            Error::MigrationError(_) => None,
 
             #[cfg(feature = "functions")]
             Error::InvalidFunctionParameterType(_, _) => None,
@@ -188,6 +224,13 @@ impl error::Error for Error {
 
             Error::FromSqlConversionFailure(_, _, ref err) |
             Error::ToSqlConversionFailure(ref err) => Some(&**err),
+
+            #[cfg(feature = "vtab")]
+            Error::InvalidFilterParameterType(_, _) |
+            Error::ModuleError(_) => None,
+
+            #[cfg(feature = "serde")]
+            Error::SerdeError(_) => None,
         }
     }
 }
@@ -50,73 +50,24 @@
 //! }
 //! ```
 use std::error::Error as StdError;
-use std::ffi::CStr;
 use std::mem;
 use std::ptr;
 use std::slice;
-use std::os::raw::{c_int, c_char, c_void};
+use std::os::raw::{c_int, c_void};
 
 use ffi;
 use ffi::sqlite3_context;
 use ffi::sqlite3_value;
 
-use types::{ToSql, ToSqlOutput, FromSql, FromSqlError, ValueRef};
+use types::{ToSql, FromSql, FromSqlError, ValueRef};
 
 use {Result, Error, Connection, str_to_cstring, InnerConnection};
-
-fn set_result<'a>(ctx: *mut sqlite3_context, result: &ToSqlOutput<'a>) {
-    let value = match *result {
-        ToSqlOutput::Borrowed(v) => v,
-        ToSqlOutput::Owned(ref v) => ValueRef::from(v),
-
-        #[cfg(feature = "blob")]
-        ToSqlOutput::ZeroBlob(len) => {
-            return unsafe { ffi::sqlite3_result_zeroblob(ctx, len) };
-        }
-    };
-
-    match value {
-        ValueRef::Null => unsafe { ffi::sqlite3_result_null(ctx) },
-        ValueRef::Integer(i) => unsafe { ffi::sqlite3_result_int64(ctx, i) },
-        ValueRef::Real(r) => unsafe { ffi::sqlite3_result_double(ctx, r) },
-        ValueRef::Text(s) => unsafe {
-            let length = s.len();
-            if length > ::std::i32::MAX as usize {
-                ffi::sqlite3_result_error_toobig(ctx);
-            } else {
-                let c_str = match str_to_cstring(s) {
-                    Ok(c_str) => c_str,
-                    // TODO sqlite3_result_error
-                    Err(_) => return ffi::sqlite3_result_error_code(ctx, ffi::SQLITE_MISUSE),
-                };
-                let destructor = if length > 0 {
-                    ffi::SQLITE_TRANSIENT()
-                } else {
-                    ffi::SQLITE_STATIC()
-                };
-                ffi::sqlite3_result_text(ctx, c_str.as_ptr(), length as c_int, destructor);
-            }
-        },
-        ValueRef::Blob(b) => unsafe {
-            let length = b.len();
-            if length > ::std::i32::MAX as usize {
-                ffi::sqlite3_result_error_toobig(ctx);
-            } else if length == 0 {
-                ffi::sqlite3_result_zeroblob(ctx, 0)
-            } else {
-                ffi::sqlite3_result_blob(ctx,
-                                         b.as_ptr() as *const c_void,
-                                         length as c_int,
-                                         ffi::SQLITE_TRANSIENT());
-            }
-        },
-    }
-}
+use context::set_result;
 
 unsafe fn report_error(ctx: *mut sqlite3_context, err: &Error) {
     // Extended constraint error codes were added in SQLite 3.7.16. We don't have an explicit
     // feature check for that, and this doesn't really warrant one. We'll use the extended code
-    // if we're on the bundled version (since it's at least 3.17.0) and the normal constraint
+    // if we're on the bundled version (since it's at least 3.38.2) and the normal constraint
     // error code if not.
     #[cfg(feature = "bundled")]
     fn constraint_error_code() -> i32 {
@@ -143,45 +94,6 @@ unsafe fn report_error(ctx: *mut sqlite3_context, err: &Error) {
     }
 }
 
-impl<'a> ValueRef<'a> {
-    unsafe fn from_value(value: *mut sqlite3_value) -> ValueRef<'a> {
-        use std::slice::from_raw_parts;
-
-        match ffi::sqlite3_value_type(value) {
-            ffi::SQLITE_NULL => ValueRef::Null,
-            ffi::SQLITE_INTEGER => ValueRef::Integer(ffi::sqlite3_value_int64(value)),
-            ffi::SQLITE_FLOAT => ValueRef::Real(ffi::sqlite3_value_double(value)),
-            ffi::SQLITE_TEXT => {
-                let text = ffi::sqlite3_value_text(value);
-                assert!(!text.is_null(),
-                        "unexpected SQLITE_TEXT value type with NULL data");
-                let s = CStr::from_ptr(text as *const c_char);
-
-                // sqlite3_value_text returns UTF8 data, so our unwrap here should be fine.
-                let s = s.to_str()
-                    .expect("sqlite3_value_text returned invalid UTF-8");
-                ValueRef::Text(s)
-            }
-            ffi::SQLITE_BLOB => {
-                let (blob, len) = (ffi::sqlite3_value_blob(value), ffi::sqlite3_value_bytes(value));
-
-                assert!(len >= 0,
-                        "unexpected negative return from sqlite3_value_bytes");
-                if len > 0 {
-                    assert!(!blob.is_null(),
-                            "unexpected SQLITE_BLOB value type with NULL data");
-                    ValueRef::Blob(from_raw_parts(blob as *const u8, len as usize))
-                } else {
-                    // The return value from sqlite3_value_blob() for a zero-length BLOB
-                    // is a NULL pointer.
-                    ValueRef::Blob(&[])
-                }
-            }
-            _ => unreachable!("sqlite3_value_type returned invalid value"),
-        }
-    }
-}
-
 unsafe extern "C" fn free_boxed_value<T>(p: *mut c_void) {
     let _: Box<T> = Box::from_raw(mem::transmute(p));
 }
@@ -277,6 +189,25 @@ pub trait Aggregate<A, T>
     fn finalize(&self, Option<A>) -> Result<T>;
 }
 
+/// WindowAggregate is the callback interface for user-defined aggregate window functions.
+///
+/// In addition to the `Aggregate` callbacks, a window function must be able to report its
+/// current value and to remove rows that leave the window (e.g., for
+/// `OVER (ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING)`).
+#[cfg(feature = "window")]
+pub trait WindowAggregate<A, T>: Aggregate<A, T>
+    where T: ToSql
+{
+    /// Returns the current value of the aggregate. Unlike `finalize`, the
+    /// aggregation context is left intact. Will be given `None` if no row is
+    /// currently part of the window.
+    fn value(&self, Option<&A>) -> Result<T>;
+
+    /// Removes a row from the current window. The arguments are the values
+    /// originally passed to `step()` for that row.
+    fn inverse(&self, &mut Context, &mut A) -> Result<()>;
+}
+
 impl Connection {
     /// Attach a user-defined scalar function to this database connection.
     ///
@@ -340,6 +271,30 @@ impl Connection {
             .create_aggregate_function(fn_name, n_arg, deterministic, aggr)
     }
 
+    /// Attach a user-defined aggregate window function to this database
+    /// connection.
+    ///
+    /// See https://sqlite.org/windowfunctions.html#udfwinfunc for more
+    /// information.
+    ///
+    /// # Failure
+    ///
+    /// Will return Err if the function could not be attached to the connection.
+    #[cfg(feature = "window")]
+    pub fn create_window_function<A, W, T>(&self,
+                                           fn_name: &str,
+                                           n_arg: c_int,
+                                           deterministic: bool,
+                                           aggr: W)
+                                           -> Result<()>
+        where W: WindowAggregate<A, T>,
+              T: ToSql
+    {
+        self.db
+            .borrow_mut()
+            .create_window_function(fn_name, n_arg, deterministic, aggr)
+    }
+
     /// Removes a user-defined function from this database connection.
     ///
     /// `fn_name` and `n_arg` should match the name and number of arguments
@@ -415,80 +370,6 @@ impl InnerConnection {
         where D: Aggregate<A, T>,
               T: ToSql
     {
-        unsafe fn aggregate_context<A>(ctx: *mut sqlite3_context,
-                                       bytes: usize)
-                                       -> Option<*mut *mut A> {
-            let pac = ffi::sqlite3_aggregate_context(ctx, bytes as c_int) as *mut *mut A;
-            if pac.is_null() {
-                return None;
-            }
-            Some(pac)
-        }
-
-        unsafe extern "C" fn call_boxed_step<A, D, T>(ctx: *mut sqlite3_context,
-                                                      argc: c_int,
-                                                      argv: *mut *mut sqlite3_value)
-            where D: Aggregate<A, T>,
-                  T: ToSql
-        {
-            let boxed_aggr: *mut D = mem::transmute(ffi::sqlite3_user_data(ctx));
-            assert!(!boxed_aggr.is_null(),
-                    "Internal error - null aggregate pointer");
-
-            let pac = match aggregate_context(ctx, ::std::mem::size_of::<*mut A>()) {
-                Some(pac) => pac,
-                None => {
-                    ffi::sqlite3_result_error_nomem(ctx);
-                    return;
-                }
-            };
-
-            if (*pac).is_null() {
-                *pac = Box::into_raw(Box::new((*boxed_aggr).init()));
-            }
-
-            let mut ctx = Context {
-                ctx: ctx,
-                args: slice::from_raw_parts(argv, argc as usize),
-            };
-
-            match (*boxed_aggr).step(&mut ctx, &mut **pac) {
-                Ok(_) => {}
-                Err(err) => report_error(ctx.ctx, &err),
-            };
-        }
-
-        unsafe extern "C" fn call_boxed_final<A, D, T>(ctx: *mut sqlite3_context)
-            where D: Aggregate<A, T>,
-                  T: ToSql
-        {
-            let boxed_aggr: *mut D = mem::transmute(ffi::sqlite3_user_data(ctx));
-            assert!(!boxed_aggr.is_null(),
-                    "Internal error - null aggregate pointer");
-
-            // Within the xFinal callback, it is customary to set N=0 in calls to
-            // sqlite3_aggregate_context(C,N) so that no pointless memory allocations occur.
-            let a: Option<A> = match aggregate_context(ctx, 0) {
-                Some(pac) => {
-                    if (*pac).is_null() {
-                        None
-                    } else {
-                        let a = Box::from_raw(*pac);
-                        Some(*a)
-                    }
-                }
-                None => None,
-            };
-
-            let t = (*boxed_aggr).finalize(a);
-            let t = t.as_ref().map(|t| ToSql::to_sql(t));
-            match t {
-                Ok(Ok(ref value)) => set_result(ctx, value),
-                Ok(Err(err)) => report_error(ctx, &err),
-                Err(err) => report_error(ctx, err),
-            }
-        }
-
         let boxed_aggr: *mut D = Box::into_raw(Box::new(aggr));
         let c_name = try!(str_to_cstring(fn_name));
         let mut flags = ffi::SQLITE_UTF8;
@@ -509,6 +390,37 @@ impl InnerConnection {
         self.decode_result(r)
     }
 
+    #[cfg(feature = "window")]
+    fn create_window_function<A, W, T>(&mut self,
+                                       fn_name: &str,
+                                       n_arg: c_int,
+                                       deterministic: bool,
+                                       aggr: W)
+                                       -> Result<()>
+        where W: WindowAggregate<A, T>,
+              T: ToSql
+    {
+        let c_name = try!(str_to_cstring(fn_name));
+        let boxed_aggr: *mut W = Box::into_raw(Box::new(aggr));
+        let mut flags = ffi::SQLITE_UTF8;
+        if deterministic {
+            flags |= ffi::SQLITE_DETERMINISTIC;
+        }
+        let r = unsafe {
+            ffi::sqlite3_create_window_function(self.db(),
+                                                c_name.as_ptr(),
+                                                n_arg,
+                                                flags,
+                                                boxed_aggr as *mut c_void,
+                                                Some(call_boxed_step::<A, W, T>),
+                                                Some(call_boxed_final::<A, W, T>),
+                                                Some(call_boxed_value::<A, W, T>),
+                                                Some(call_boxed_inverse::<A, W, T>),
+                                                Some(free_boxed_value::<W>))
+        };
+        self.decode_result(r)
+    }
+
     fn remove_function(&mut self, fn_name: &str, n_arg: c_int) -> Result<()> {
         let c_name = try!(str_to_cstring(fn_name));
         let r = unsafe {
@@ -526,6 +438,139 @@ impl InnerConnection {
     }
 }
 
+unsafe fn aggregate_context<A>(ctx: *mut sqlite3_context, bytes: usize) -> Option<*mut *mut A> {
+    let pac = ffi::sqlite3_aggregate_context(ctx, bytes as c_int) as *mut *mut A;
+    if pac.is_null() {
+        return None;
+    }
+    Some(pac)
+}
+
+unsafe extern "C" fn call_boxed_step<A, D, T>(ctx: *mut sqlite3_context,
+                                              argc: c_int,
+                                              argv: *mut *mut sqlite3_value)
+    where D: Aggregate<A, T>,
+          T: ToSql
+{
+    let boxed_aggr: *mut D = mem::transmute(ffi::sqlite3_user_data(ctx));
+    assert!(!boxed_aggr.is_null(),
+            "Internal error - null aggregate pointer");
+
+    let pac = match aggregate_context(ctx, ::std::mem::size_of::<*mut A>()) {
+        Some(pac) => pac,
+        None => {
+            ffi::sqlite3_result_error_nomem(ctx);
+            return;
+        }
+    };
+
+    if (*pac).is_null() {
+        *pac = Box::into_raw(Box::new((*boxed_aggr).init()));
+    }
+
+    let mut ctx = Context {
+        ctx: ctx,
+        args: slice::from_raw_parts(argv, argc as usize),
+    };
+
+    match (*boxed_aggr).step(&mut ctx, &mut **pac) {
+        Ok(_) => {}
+        Err(err) => report_error(ctx.ctx, &err),
+    };
+}
+
+unsafe extern "C" fn call_boxed_final<A, D, T>(ctx: *mut sqlite3_context)
+    where D: Aggregate<A, T>,
+          T: ToSql
+{
+    let boxed_aggr: *mut D = mem::transmute(ffi::sqlite3_user_data(ctx));
+    assert!(!boxed_aggr.is_null(),
+            "Internal error - null aggregate pointer");
+
+    // Within the xFinal callback, it is customary to set N=0 in calls to
+    // sqlite3_aggregate_context(C,N) so that no pointless memory allocations occur.
+    let a: Option<A> = match aggregate_context(ctx, 0) {
+        Some(pac) => {
+            if (*pac).is_null() {
+                None
+            } else {
+                let a = Box::from_raw(*pac);
+                Some(*a)
+            }
+        }
+        None => None,
+    };
+
+    let t = (*boxed_aggr).finalize(a);
+    let t = t.as_ref().map(|t| ToSql::to_sql(t));
+    match t {
+        Ok(Ok(ref value)) => set_result(ctx, value),
+        Ok(Err(err)) => report_error(ctx, &err),
+        Err(err) => report_error(ctx, err),
+    }
+}
+
+#[cfg(feature = "window")]
+unsafe extern "C" fn call_boxed_inverse<A, W, T>(ctx: *mut sqlite3_context,
+                                                 argc: c_int,
+                                                 argv: *mut *mut sqlite3_value)
+    where W: WindowAggregate<A, T>,
+          T: ToSql
+{
+    let boxed_aggr: *mut W = ffi::sqlite3_user_data(ctx) as *mut W;
+    assert!(!boxed_aggr.is_null(),
+            "Internal error - null aggregate pointer");
+
+    let pac = match aggregate_context(ctx, ::std::mem::size_of::<*mut A>()) {
+        Some(pac) => pac,
+        None => {
+            ffi::sqlite3_result_error_nomem(ctx);
+            return;
+        }
+    };
+    // xInverse is only ever invoked for rows previously passed to xStep.
+    assert!(!(*pac).is_null(), "Internal error - null aggregate context");
+
+    let mut ctx = Context {
+        ctx: ctx,
+        args: slice::from_raw_parts(argv, argc as usize),
+    };
+
+    match (*boxed_aggr).inverse(&mut ctx, &mut **pac) {
+        Ok(_) => {}
+        Err(err) => report_error(ctx.ctx, &err),
+    };
+}
+
+#[cfg(feature = "window")]
+unsafe extern "C" fn call_boxed_value<A, W, T>(ctx: *mut sqlite3_context)
+    where W: WindowAggregate<A, T>,
+          T: ToSql
+{
+    let boxed_aggr: *mut W = ffi::sqlite3_user_data(ctx) as *mut W;
+    assert!(!boxed_aggr.is_null(),
+            "Internal error - null aggregate pointer");
+
+    let a: Option<&A> = match aggregate_context(ctx, 0) {
+        Some(pac) => {
+            if (*pac).is_null() {
+                None
+            } else {
+                Some(&**pac)
+            }
+        }
+        None => None,
+    };
+
+    let t = (*boxed_aggr).value(a);
+    let t = t.as_ref().map(|t| ToSql::to_sql(t));
+    match t {
+        Ok(Ok(ref value)) => set_result(ctx, value),
+        Ok(Err(err)) => report_error(ctx, &err),
+        Err(err) => report_error(ctx, err),
+    }
+}
+
 #[cfg(test)]
 mod test {
     extern crate regex;
@@ -537,6 +582,8 @@ mod test {
 
     use {Connection, Error, Result};
     use functions::{Aggregate, Context};
+    #[cfg(feature = "window")]
+    use functions::WindowAggregate;
 
     fn half(ctx: &Context) -> Result<c_double> {
         assert!(ctx.len() == 1, "called with unexpected number of arguments");
@@ -768,4 +815,56 @@ mod test {
         let result: i64 = db.query_row(single_sum, &[], |r| r.get(0)).unwrap();
         assert_eq!(2, result);
     }
+
+    #[cfg(feature = "window")]
+    impl WindowAggregate<i64, Option<i64>> for Sum {
+        fn inverse(&self, ctx: &mut Context, sum: &mut i64) -> Result<()> {
+            *sum -= try!(ctx.get::<i64>(0));
+            Ok(())
+        }
+
+        fn value(&self, sum: Option<&i64>) -> Result<Option<i64>> {
+            Ok(sum.cloned())
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "window")]
+    fn test_window() {
+        let db = Connection::open_in_memory().unwrap();
+        db.create_window_function("sumint", 1, true, Sum).unwrap();
+        db.execute_batch("CREATE TABLE t3(x, y);
+                          INSERT INTO t3 VALUES('a', 4),
+                                               ('b', 5),
+                                               ('c', 3),
+                                               ('d', 8),
+                                               ('e', 1);")
+            .unwrap();
+
+        let mut stmt = db.prepare("SELECT x, sumint(y) OVER (
+                                     ORDER BY x ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING
+                                   ) AS sum_y
+                                   FROM t3 ORDER BY x;")
+            .unwrap();
+
+        let results: Vec<(String, i64)> = stmt.query_map(&[], |row| (row.get(0), row.get(1)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        let expected = vec![("a", 9), ("b", 12), ("c", 16), ("d", 12), ("e", 9)]
+            .into_iter()
+            .map(|(s, i)| (s.to_owned(), i))
+            .collect::<Vec<_>>();
+        assert_eq!(expected, results);
+    }
+
+    #[test]
+    #[cfg(feature = "window")]
+    fn test_invalid_window_function_name() {
+        let db = Connection::open_in_memory().unwrap();
+        match db.create_window_function("sum\0int", 1, true, Sum) {
+            Err(Error::NulError(_)) => {}
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
 }
@@ -0,0 +1,420 @@
+//! Authorizer Callbacks
+//!
+//! See [Compile-Time Authorization Callbacks](https://sqlite.org/c3ref/set_authorizer.html).
+
+use std::borrow::Cow;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use ffi;
+
+use {Connection, InnerConnection};
+
+// Not defined by the bindings of SQLite versions older than 3.8.3.
+const SQLITE_RECURSIVE: c_int = 33;
+
+/// The context of an authorization request.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AuthContext<'c> {
+    /// The action to be authorized.
+    pub action: AuthAction<'c>,
+    /// The database name ("main", "temp", ...), if applicable.
+    pub database_name: Option<&'c str>,
+    /// The inner-most trigger or view responsible for the access attempt,
+    /// or `None` if the access attempt comes directly from top-level SQL.
+    pub accessor: Option<&'c str>,
+}
+
+/// An action to be authorized, decoded from the SQLite action code and its
+/// arguments.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AuthAction<'c> {
+    /// An action code or argument combination this version of rusqlite
+    /// doesn't know about.
+    Unknown {
+        /// The raw action code.
+        code: i32,
+        /// The first argument of the action.
+        arg1: Option<&'c str>,
+        /// The second argument of the action.
+        arg2: Option<&'c str>,
+    },
+    CreateIndex { index: &'c str, table: &'c str },
+    CreateTable { table: &'c str },
+    CreateTempIndex { index: &'c str, table: &'c str },
+    CreateTempTable { table: &'c str },
+    CreateTempTrigger { trigger: &'c str, table: &'c str },
+    CreateTempView { view: &'c str },
+    CreateTrigger { trigger: &'c str, table: &'c str },
+    CreateView { view: &'c str },
+    Delete { table: &'c str },
+    DropIndex { index: &'c str, table: &'c str },
+    DropTable { table: &'c str },
+    DropTempIndex { index: &'c str, table: &'c str },
+    DropTempTable { table: &'c str },
+    DropTempTrigger { trigger: &'c str, table: &'c str },
+    DropTempView { view: &'c str },
+    DropTrigger { trigger: &'c str, table: &'c str },
+    DropView { view: &'c str },
+    Insert { table: &'c str },
+    Pragma { name: &'c str, value: Option<&'c str> },
+    Read { table: &'c str, column: &'c str },
+    Select,
+    Transaction { operation: TransactionOperation },
+    Update { table: &'c str, column: &'c str },
+    Attach { filename: &'c str },
+    Detach { database: &'c str },
+    AlterTable { database: &'c str, table: &'c str },
+    Reindex { index: &'c str },
+    Analyze { table: &'c str },
+    CreateVTable { table: &'c str, module: &'c str },
+    DropVTable { table: &'c str, module: &'c str },
+    Function { name: &'c str },
+    Savepoint { operation: TransactionOperation, name: &'c str },
+    Recursive,
+}
+
+impl<'c> AuthAction<'c> {
+    fn from_raw(code: i32, arg1: Option<&'c str>, arg2: Option<&'c str>) -> AuthAction<'c> {
+        match (code, arg1, arg2) {
+            (ffi::SQLITE_CREATE_INDEX, Some(index), Some(table)) => {
+                AuthAction::CreateIndex {
+                    index: index,
+                    table: table,
+                }
+            }
+            (ffi::SQLITE_CREATE_TABLE, Some(table), _) => AuthAction::CreateTable { table: table },
+            (ffi::SQLITE_CREATE_TEMP_INDEX, Some(index), Some(table)) => {
+                AuthAction::CreateTempIndex {
+                    index: index,
+                    table: table,
+                }
+            }
+            (ffi::SQLITE_CREATE_TEMP_TABLE, Some(table), _) => {
+                AuthAction::CreateTempTable { table: table }
+            }
+            (ffi::SQLITE_CREATE_TEMP_TRIGGER, Some(trigger), Some(table)) => {
+                AuthAction::CreateTempTrigger {
+                    trigger: trigger,
+                    table: table,
+                }
+            }
+            (ffi::SQLITE_CREATE_TEMP_VIEW, Some(view), _) => AuthAction::CreateTempView { view: view },
+            (ffi::SQLITE_CREATE_TRIGGER, Some(trigger), Some(table)) => {
+                AuthAction::CreateTrigger {
+                    trigger: trigger,
+                    table: table,
+                }
+            }
+            (ffi::SQLITE_CREATE_VIEW, Some(view), _) => AuthAction::CreateView { view: view },
+            (ffi::SQLITE_DELETE, Some(table), _) => AuthAction::Delete { table: table },
+            (ffi::SQLITE_DROP_INDEX, Some(index), Some(table)) => {
+                AuthAction::DropIndex {
+                    index: index,
+                    table: table,
+                }
+            }
+            (ffi::SQLITE_DROP_TABLE, Some(table), _) => AuthAction::DropTable { table: table },
+            (ffi::SQLITE_DROP_TEMP_INDEX, Some(index), Some(table)) => {
+                AuthAction::DropTempIndex {
+                    index: index,
+                    table: table,
+                }
+            }
+            (ffi::SQLITE_DROP_TEMP_TABLE, Some(table), _) => AuthAction::DropTempTable { table: table },
+            (ffi::SQLITE_DROP_TEMP_TRIGGER, Some(trigger), Some(table)) => {
+                AuthAction::DropTempTrigger {
+                    trigger: trigger,
+                    table: table,
+                }
+            }
+            (ffi::SQLITE_DROP_TEMP_VIEW, Some(view), _) => AuthAction::DropTempView { view: view },
+            (ffi::SQLITE_DROP_TRIGGER, Some(trigger), Some(table)) => {
+                AuthAction::DropTrigger {
+                    trigger: trigger,
+                    table: table,
+                }
+            }
+            (ffi::SQLITE_DROP_VIEW, Some(view), _) => AuthAction::DropView { view: view },
+            (ffi::SQLITE_INSERT, Some(table), _) => AuthAction::Insert { table: table },
+            (ffi::SQLITE_PRAGMA, Some(name), value) => {
+                AuthAction::Pragma {
+                    name: name,
+                    value: value,
+                }
+            }
+            (ffi::SQLITE_READ, Some(table), Some(column)) => {
+                AuthAction::Read {
+                    table: table,
+                    column: column,
+                }
+            }
+            (ffi::SQLITE_SELECT, _, _) => AuthAction::Select,
+            (ffi::SQLITE_TRANSACTION, Some(operation), _) => {
+                AuthAction::Transaction { operation: TransactionOperation::from_str(operation) }
+            }
+            (ffi::SQLITE_UPDATE, Some(table), Some(column)) => {
+                AuthAction::Update {
+                    table: table,
+                    column: column,
+                }
+            }
+            (ffi::SQLITE_ATTACH, Some(filename), _) => AuthAction::Attach { filename: filename },
+            (ffi::SQLITE_DETACH, Some(database), _) => AuthAction::Detach { database: database },
+            (ffi::SQLITE_ALTER_TABLE, Some(database), Some(table)) => {
+                AuthAction::AlterTable {
+                    database: database,
+                    table: table,
+                }
+            }
+            (ffi::SQLITE_REINDEX, Some(index), _) => AuthAction::Reindex { index: index },
+            (ffi::SQLITE_ANALYZE, Some(table), _) => AuthAction::Analyze { table: table },
+            (ffi::SQLITE_CREATE_VTABLE, Some(table), Some(module)) => {
+                AuthAction::CreateVTable {
+                    table: table,
+                    module: module,
+                }
+            }
+            (ffi::SQLITE_DROP_VTABLE, Some(table), Some(module)) => {
+                AuthAction::DropVTable {
+                    table: table,
+                    module: module,
+                }
+            }
+            (ffi::SQLITE_FUNCTION, _, Some(name)) => AuthAction::Function { name: name },
+            (ffi::SQLITE_SAVEPOINT, Some(operation), Some(name)) => {
+                AuthAction::Savepoint {
+                    operation: TransactionOperation::from_str(operation),
+                    name: name,
+                }
+            }
+            (SQLITE_RECURSIVE, _, _) => AuthAction::Recursive,
+            (code, arg1, arg2) => {
+                AuthAction::Unknown {
+                    code: code,
+                    arg1: arg1,
+                    arg2: arg2,
+                }
+            }
+        }
+    }
+}
+
+/// The operation of a `Transaction` or `Savepoint` action.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransactionOperation {
+    Unknown,
+    Begin,
+    Commit,
+    Release,
+    Rollback,
+}
+
+impl TransactionOperation {
+    fn from_str(op: &str) -> TransactionOperation {
+        match op {
+            "BEGIN" => TransactionOperation::Begin,
+            "COMMIT" => TransactionOperation::Commit,
+            "RELEASE" => TransactionOperation::Release,
+            "ROLLBACK" => TransactionOperation::Rollback,
+            _ => TransactionOperation::Unknown,
+        }
+    }
+}
+
+/// The return value of an authorizer callback.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Authorization {
+    /// Allow the action.
+    Allow,
+    /// Don't allow the action, but continue preparing the statement: a column
+    /// read yields NULL, a `DELETE` from a table is silently skipped, ...
+    Ignore,
+    /// Reject the whole statement with an error.
+    Deny,
+}
+
+impl Authorization {
+    fn into_raw(self) -> c_int {
+        match self {
+            Authorization::Allow => ffi::SQLITE_OK,
+            Authorization::Ignore => ffi::SQLITE_IGNORE,
+            Authorization::Deny => ffi::SQLITE_DENY,
+        }
+    }
+}
+
+impl Connection {
+    /// Register an authorizer callback, invoked while statements are being
+    /// prepared to check each action they would perform.
+    ///
+    /// A statement with a denied action fails to prepare with an
+    /// `ErrorCode::AuthorizationForStatementDenied` error. Changing the
+    /// authorizer expires the statements already prepared, including cached
+    /// ones: they are prepared and authorized again when they are next run.
+    pub fn authorizer<F>(&self, hook: Option<F>)
+        where F: for<'c> FnMut(AuthContext<'c>) -> Authorization + Send + 'static
+    {
+        self.db.borrow_mut().authorizer(hook);
+    }
+}
+
+impl InnerConnection {
+    fn authorizer<F>(&mut self, hook: Option<F>)
+        where F: for<'c> FnMut(AuthContext<'c>) -> Authorization + Send + 'static
+    {
+        unsafe fn to_str<'a>(p_str: *const c_char) -> Option<Cow<'a, str>> {
+            if p_str.is_null() {
+                None
+            } else {
+                Some(String::from_utf8_lossy(CStr::from_ptr(p_str).to_bytes()))
+            }
+        }
+
+        unsafe extern "C" fn call_boxed_closure(p_arg: *mut c_void,
+                                                action_code: c_int,
+                                                arg1: *const c_char,
+                                                arg2: *const c_char,
+                                                db_name: *const c_char,
+                                                accessor: *const c_char)
+                                                -> c_int {
+            let boxed_hook = p_arg as *mut Box<FnMut(AuthContext) -> Authorization + Send>;
+
+            let arg1 = to_str(arg1);
+            let arg2 = to_str(arg2);
+            let db_name = to_str(db_name);
+            let accessor = to_str(accessor);
+            let auth_ctx = AuthContext {
+                action: AuthAction::from_raw(action_code,
+                                             arg1.as_ref().map(|s| &**s),
+                                             arg2.as_ref().map(|s| &**s)),
+                database_name: db_name.as_ref().map(|s| &**s),
+                accessor: accessor.as_ref().map(|s| &**s),
+            };
+
+            let r = catch_unwind(AssertUnwindSafe(|| (*boxed_hook)(auth_ctx)));
+            // A panicking authorizer denies the action.
+            r.unwrap_or(Authorization::Deny).into_raw()
+        }
+
+        let boxed_hook = hook.map(|f| {
+            let hook: Box<FnMut(AuthContext) -> Authorization + Send> = Box::new(f);
+            Box::new(hook)
+        });
+        match boxed_hook {
+            Some(ref hook) => {
+                let p_arg = &**hook as *const Box<FnMut(AuthContext) -> Authorization + Send> as
+                            *mut c_void;
+                unsafe { ffi::sqlite3_set_authorizer(self.db(), Some(call_boxed_closure), p_arg) };
+            }
+            None => unsafe {
+                ffi::sqlite3_set_authorizer(self.db(), None, ptr::null_mut());
+            },
+        }
+        self.authorizer = boxed_hook;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use ffi::ErrorCode;
+    use hooks::{AuthAction, AuthContext, Authorization, TransactionOperation};
+    use {Connection, Error};
+
+    #[test]
+    fn test_authorizer_deny() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo (x INTEGER, secret TEXT);
+                          INSERT INTO foo VALUES (1, 'hidden');")
+            .unwrap();
+
+        db.authorizer(Some(|ctx: AuthContext| match ctx.action {
+                               AuthAction::Read { table: "foo", column: "secret" } => {
+                                   Authorization::Ignore
+                               }
+                               AuthAction::Pragma { value: Some(_), .. } |
+                               AuthAction::Attach { .. } => Authorization::Deny,
+                               _ => Authorization::Allow,
+                           }));
+
+        let row: (i64, Option<String>) =
+            db.query_row("SELECT x, secret FROM foo", &[], |r| (r.get(0), r.get(1)))
+                .unwrap();
+        assert_eq!((1, None), row);
+
+        db.prepare("PRAGMA user_version").unwrap();
+        for sql in &["PRAGMA user_version = 1", "ATTACH ':memory:' AS other"] {
+            match db.prepare(sql) {
+                Err(Error::SqliteFailure(err, _)) => {
+                    assert_eq!(ErrorCode::AuthorizationForStatementDenied, err.code)
+                }
+                _ => panic!("Expected {} to be denied", sql),
+            }
+        }
+
+        db.authorizer(None::<fn(AuthContext) -> Authorization>);
+        db.prepare("PRAGMA user_version = 1").unwrap();
+    }
+
+    #[test]
+    fn test_authorizer_expires_prepared_statements() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo (x INTEGER); INSERT INTO foo VALUES (1);").unwrap();
+        {
+            let mut stmt = db.prepare_cached("SELECT x FROM foo").unwrap();
+            assert_eq!(1i64, stmt.query_row(&[], |r| r.get::<_, i64>(0)).unwrap());
+        }
+
+        db.authorizer(Some(|ctx: AuthContext| match ctx.action {
+                               AuthAction::Read { table: "foo", .. } => Authorization::Deny,
+                               _ => Authorization::Allow,
+                           }));
+
+        let mut stmt = db.prepare_cached("SELECT x FROM foo").unwrap();
+        match stmt.query_row(&[], |r| r.get::<_, i64>(0)) {
+            Err(Error::SqliteFailure(err, _)) => {
+                assert_eq!(ErrorCode::AuthorizationForStatementDenied, err.code)
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_authorizer_actions() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo (x INTEGER);
+                          CREATE VIEW bar AS SELECT x FROM foo;")
+            .unwrap();
+
+        let actions = Arc::new(Mutex::new(Vec::new()));
+        let hook_actions = actions.clone();
+        db.authorizer(Some(move |ctx: AuthContext| {
+                               let accessor = ctx.accessor.map(|s| s.to_owned());
+                               let action = format!("{:?} {:?}", ctx.action, accessor);
+                               hook_actions.lock().unwrap().push(action);
+                               Authorization::Allow
+                           }));
+
+        db.execute_batch("BEGIN; SELECT x FROM bar; COMMIT;").unwrap();
+        let actions = actions.lock().unwrap();
+        let begin = AuthAction::Transaction { operation: TransactionOperation::Begin };
+        let commit = AuthAction::Transaction { operation: TransactionOperation::Commit };
+        let read_view = AuthAction::Read {
+            table: "bar",
+            column: "x",
+        };
+        let read_table = AuthAction::Read {
+            table: "foo",
+            column: "x",
+        };
+        assert_eq!(Some(&format!("{:?} None", begin)), actions.first());
+        assert_eq!(Some(&format!("{:?} None", commit)), actions.last());
+        assert!(actions.contains(&format!("{:?} None", read_view)));
+        // Reads through the view are attributed to it.
+        assert!(actions.contains(&format!("{:?} Some(\"bar\")", read_table)));
+    }
+}
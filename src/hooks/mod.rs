@@ -0,0 +1,335 @@
+//! Commit, Data Change and Rollback Notification Callbacks, Query Progress Callbacks,
+//! Authorizer Callbacks
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use ffi;
+
+use {Connection, InnerConnection};
+
+mod authorizer;
+#[cfg(feature = "preupdate_hook")]
+mod preupdate_hook;
+
+pub use self::authorizer::{AuthAction, AuthContext, Authorization, TransactionOperation};
+#[cfg(feature = "preupdate_hook")]
+pub use self::preupdate_hook::PreUpdateCase;
+
+/// Action codes passed to an update hook.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(i32)]
+pub enum Action {
+    Unknown = -1,
+    Delete = ffi::SQLITE_DELETE,
+    Insert = ffi::SQLITE_INSERT,
+    Update = ffi::SQLITE_UPDATE,
+}
+
+impl From<i32> for Action {
+    fn from(code: i32) -> Action {
+        match code {
+            ffi::SQLITE_DELETE => Action::Delete,
+            ffi::SQLITE_INSERT => Action::Insert,
+            ffi::SQLITE_UPDATE => Action::Update,
+            _ => Action::Unknown,
+        }
+    }
+}
+
+impl Connection {
+    /// Register a callback function to be invoked whenever a transaction is committed.
+    ///
+    /// The callback returns `true` to rollback the transaction instead of
+    /// committing it; the `COMMIT` (e.g., `Transaction::commit`) then fails
+    /// with a constraint error.
+    pub fn commit_hook<F>(&self, hook: Option<F>)
+        where F: FnMut() -> bool + Send + 'static
+    {
+        self.db.borrow_mut().commit_hook(hook);
+    }
+
+    /// Register a callback function to be invoked whenever a transaction is rolled back.
+    pub fn rollback_hook<F>(&self, hook: Option<F>)
+        where F: FnMut() + Send + 'static
+    {
+        self.db.borrow_mut().rollback_hook(hook);
+    }
+
+    /// Register a callback function to be invoked whenever a row is updated,
+    /// inserted or deleted in a rowid table.
+    ///
+    /// The callback parameters are:
+    ///
+    /// - the type of database update (`Insert`, `Update` or `Delete`),
+    /// - the name of the database ("main", "temp", ...),
+    /// - the name of the table that is updated,
+    /// - the ROWID of the row that is updated.
+    ///
+    /// The callback must not do anything that will modify the database
+    /// connection that invoked it.
+    pub fn update_hook<F>(&self, hook: Option<F>)
+        where F: FnMut(Action, &str, &str, i64) + Send + 'static
+    {
+        self.db.borrow_mut().update_hook(hook);
+    }
+
+    /// Register a callback function to be invoked periodically during
+    /// long-running queries.
+    ///
+    /// The callback is invoked approximately every `num_ops` virtual machine
+    /// instructions. If the callback returns `true`, the current statement is
+    /// aborted and fails with `SQLITE_INTERRUPT` (`ErrorCode::OperationInterrupted`).
+    /// A `num_ops` less than 1 or a `None` handler disables the progress handler.
+    pub fn progress_handler<F>(&self, num_ops: i32, handler: Option<F>)
+        where F: FnMut() -> bool + Send + 'static
+    {
+        self.db.borrow_mut().progress_handler(num_ops, handler);
+    }
+}
+
+impl InnerConnection {
+    fn commit_hook<F>(&mut self, hook: Option<F>)
+        where F: FnMut() -> bool + Send + 'static
+    {
+        unsafe extern "C" fn call_boxed_closure(p_arg: *mut c_void) -> c_int {
+            let boxed_hook = p_arg as *mut Box<FnMut() -> bool + Send>;
+            let r = catch_unwind(AssertUnwindSafe(|| (*boxed_hook)()));
+            // A panicking hook rolls the transaction back.
+            if let Ok(false) = r { 0 } else { 1 }
+        }
+
+        let boxed_hook = hook.map(|f| {
+            let hook: Box<FnMut() -> bool + Send> = Box::new(f);
+            Box::new(hook)
+        });
+        match boxed_hook {
+            Some(ref hook) => {
+                let p_arg = &**hook as *const Box<FnMut() -> bool + Send> as *mut c_void;
+                unsafe { ffi::sqlite3_commit_hook(self.db(), Some(call_boxed_closure), p_arg) };
+            }
+            None => unsafe {
+                ffi::sqlite3_commit_hook(self.db(), None, ptr::null_mut());
+            },
+        }
+        self.commit_hook = boxed_hook;
+    }
+
+    fn rollback_hook<F>(&mut self, hook: Option<F>)
+        where F: FnMut() + Send + 'static
+    {
+        unsafe extern "C" fn call_boxed_closure(p_arg: *mut c_void) {
+            let boxed_hook = p_arg as *mut Box<FnMut() + Send>;
+            let _ = catch_unwind(AssertUnwindSafe(|| (*boxed_hook)()));
+        }
+
+        let boxed_hook = hook.map(|f| {
+            let hook: Box<FnMut() + Send> = Box::new(f);
+            Box::new(hook)
+        });
+        match boxed_hook {
+            Some(ref hook) => {
+                let p_arg = &**hook as *const Box<FnMut() + Send> as *mut c_void;
+                unsafe { ffi::sqlite3_rollback_hook(self.db(), Some(call_boxed_closure), p_arg) };
+            }
+            None => unsafe {
+                ffi::sqlite3_rollback_hook(self.db(), None, ptr::null_mut());
+            },
+        }
+        self.rollback_hook = boxed_hook;
+    }
+
+    fn update_hook<F>(&mut self, hook: Option<F>)
+        where F: FnMut(Action, &str, &str, i64) + Send + 'static
+    {
+        unsafe extern "C" fn call_boxed_closure(p_arg: *mut c_void,
+                                                action_code: c_int,
+                                                db_str: *const c_char,
+                                                tbl_str: *const c_char,
+                                                row_id: i64) {
+            let boxed_hook = p_arg as *mut Box<FnMut(Action, &str, &str, i64) + Send>;
+
+            let action = Action::from(action_code);
+            let db_name = String::from_utf8_lossy(CStr::from_ptr(db_str).to_bytes());
+            let tbl_name = String::from_utf8_lossy(CStr::from_ptr(tbl_str).to_bytes());
+
+            let _ = catch_unwind(AssertUnwindSafe(|| {
+                (*boxed_hook)(action, &db_name, &tbl_name, row_id)
+            }));
+        }
+
+        let boxed_hook = hook.map(|f| {
+            let hook: Box<FnMut(Action, &str, &str, i64) + Send> = Box::new(f);
+            Box::new(hook)
+        });
+        match boxed_hook {
+            Some(ref hook) => {
+                let p_arg = &**hook as *const Box<FnMut(Action, &str, &str, i64) + Send> as
+                            *mut c_void;
+                unsafe { ffi::sqlite3_update_hook(self.db(), Some(call_boxed_closure), p_arg) };
+            }
+            None => unsafe {
+                ffi::sqlite3_update_hook(self.db(), None, ptr::null_mut());
+            },
+        }
+        self.update_hook = boxed_hook;
+    }
+
+    fn progress_handler<F>(&mut self, num_ops: c_int, handler: Option<F>)
+        where F: FnMut() -> bool + Send + 'static
+    {
+        unsafe extern "C" fn call_boxed_closure(p_arg: *mut c_void) -> c_int {
+            let boxed_handler = p_arg as *mut Box<FnMut() -> bool + Send>;
+            let r = catch_unwind(AssertUnwindSafe(|| (*boxed_handler)()));
+            // A panicking handler interrupts the statement.
+            if let Ok(false) = r { 0 } else { 1 }
+        }
+
+        let boxed_handler = handler.map(|f| {
+            let handler: Box<FnMut() -> bool + Send> = Box::new(f);
+            Box::new(handler)
+        });
+        match boxed_handler {
+            Some(ref handler) => {
+                let p_arg = &**handler as *const Box<FnMut() -> bool + Send> as *mut c_void;
+                unsafe {
+                    ffi::sqlite3_progress_handler(self.db(),
+                                                  num_ops,
+                                                  Some(call_boxed_closure),
+                                                  p_arg)
+                };
+            }
+            None => unsafe {
+                ffi::sqlite3_progress_handler(self.db(), num_ops, None, ptr::null_mut());
+            },
+        }
+        self.progress_handler = boxed_handler;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use super::Action;
+    use {Connection, Error, ErrorCode};
+
+    #[test]
+    fn test_commit_hook() {
+        let db = Connection::open_in_memory().unwrap();
+
+        let called = Arc::new(AtomicBool::new(false));
+        let hook_called = called.clone();
+        db.commit_hook(Some(move || {
+                                hook_called.store(true, Ordering::SeqCst);
+                                false
+                            }));
+        db.execute_batch("BEGIN; CREATE TABLE foo (t TEXT); COMMIT;").unwrap();
+        assert!(called.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_commit_hook_rollback() {
+        let mut db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo (t TEXT);").unwrap();
+
+        let rolled_back = Arc::new(AtomicBool::new(false));
+        let hook_rolled_back = rolled_back.clone();
+        db.rollback_hook(Some(move || hook_rolled_back.store(true, Ordering::SeqCst)));
+        db.commit_hook(Some(|| true));
+
+        {
+            let tx = db.transaction().unwrap();
+            tx.execute("INSERT INTO foo VALUES ('lisa')", &[]).unwrap();
+            match tx.commit().unwrap_err() {
+                Error::SqliteFailure(err, _) => {
+                    assert_eq!(err.code, ErrorCode::ConstraintViolation)
+                }
+                err => panic!("Unexpected error {}", err),
+            }
+        }
+        assert!(rolled_back.load(Ordering::SeqCst));
+
+        db.commit_hook(None::<fn() -> bool>);
+        let count: i64 = db.query_row("SELECT COUNT(*) FROM foo", &[], |r| r.get(0)).unwrap();
+        assert_eq!(0, count);
+    }
+
+    #[test]
+    fn test_rollback_hook() {
+        let db = Connection::open_in_memory().unwrap();
+
+        let called = Arc::new(AtomicBool::new(false));
+        let hook_called = called.clone();
+        db.rollback_hook(Some(move || hook_called.store(true, Ordering::SeqCst)));
+        db.execute_batch("BEGIN; CREATE TABLE foo (t TEXT); ROLLBACK;").unwrap();
+        assert!(called.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_update_hook() {
+        let db = Connection::open_in_memory().unwrap();
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let hook_changes = changes.clone();
+        db.update_hook(Some(move |action, db: &str, tbl: &str, row_id| {
+                                hook_changes
+                                    .lock()
+                                    .unwrap()
+                                    .push((action, db.to_owned(), tbl.to_owned(), row_id));
+                            }));
+        db.execute_batch("CREATE TABLE foo (t TEXT);
+                          INSERT INTO foo VALUES ('lisa');
+                          UPDATE foo SET t = 'bart' WHERE rowid = 1;
+                          DELETE FROM foo WHERE rowid = 1;")
+            .unwrap();
+        assert_eq!(*changes.lock().unwrap(),
+                   vec![(Action::Insert, "main".to_owned(), "foo".to_owned(), 1),
+                        (Action::Update, "main".to_owned(), "foo".to_owned(), 1),
+                        (Action::Delete, "main".to_owned(), "foo".to_owned(), 1)]);
+
+        db.update_hook(None::<fn(Action, &str, &str, i64)>);
+        db.execute_batch("INSERT INTO foo VALUES ('maggie')").unwrap();
+        assert_eq!(3, changes.lock().unwrap().len());
+    }
+
+    #[test]
+    fn test_progress_handler() {
+        let db = Connection::open_in_memory().unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = calls.clone();
+        db.progress_handler(1,
+                            Some(move || {
+                                     handler_calls.fetch_add(1, Ordering::SeqCst);
+                                     false
+                                 }));
+        db.execute_batch("BEGIN; CREATE TABLE foo (t TEXT); COMMIT;").unwrap();
+        assert!(calls.load(Ordering::SeqCst) > 0);
+
+        db.progress_handler(0, None::<fn() -> bool>);
+        let before = calls.load(Ordering::SeqCst);
+        db.execute_batch("INSERT INTO foo VALUES ('lisa')").unwrap();
+        assert_eq!(before, calls.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_progress_handler_interrupt() {
+        let db = Connection::open_in_memory().unwrap();
+
+        db.progress_handler(100, Some(|| true));
+        let r = db.query_row("WITH RECURSIVE c(x) AS (VALUES(1) UNION ALL SELECT x + 1 FROM c)
+                              SELECT COUNT(*) FROM c",
+                             &[],
+                             |r| r.get::<_, i64>(0));
+        match r.unwrap_err() {
+            Error::SqliteFailure(err, _) => {
+                assert_eq!(err.code, ErrorCode::OperationInterrupted)
+            }
+            err => panic!("Unexpected error {}", err),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Pre-update Hook
+//!
+//! Requires SQLite to be compiled with `SQLITE_ENABLE_PREUPDATE_HOOK` (the
+//! `bundled` feature takes care of it).
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use error::error_from_sqlite_code;
+use ffi;
+use hooks::Action;
+use types::ValueRef;
+use {Connection, InnerConnection, Result};
+
+/// The state of a row about to be changed, as seen from a pre-update hook.
+///
+/// Column values are only accessible while the hook is running.
+pub struct PreUpdateCase {
+    db: *mut ffi::sqlite3,
+    action: Action,
+    old_row_id: i64,
+    new_row_id: i64,
+}
+
+impl PreUpdateCase {
+    /// The kind of change about to be made.
+    pub fn action(&self) -> Action {
+        self.action
+    }
+
+    /// The rowid of the row being deleted or updated, or `None` for an insert.
+    pub fn old_row_id(&self) -> Option<i64> {
+        match self.action {
+            Action::Delete | Action::Update => Some(self.old_row_id),
+            _ => None,
+        }
+    }
+
+    /// The rowid of the row being inserted or updated, or `None` for a delete.
+    pub fn new_row_id(&self) -> Option<i64> {
+        match self.action {
+            Action::Insert | Action::Update => Some(self.new_row_id),
+            _ => None,
+        }
+    }
+
+    /// The number of columns in the row being changed.
+    pub fn count(&self) -> i32 {
+        unsafe { ffi::sqlite3_preupdate_count(self.db) }
+    }
+
+    /// The trigger depth of the change: `0` for a direct change, `1` for a
+    /// change made by a top-level trigger, and so on.
+    pub fn depth(&self) -> i32 {
+        unsafe { ffi::sqlite3_preupdate_depth(self.db) }
+    }
+
+    /// The value of the `i`th column of the row before the change.
+    ///
+    /// Fails with `SQLITE_MISUSE` for an insert and `SQLITE_RANGE` if `i` is
+    /// out of bounds.
+    pub fn old(&self, i: i32) -> Result<ValueRef> {
+        let mut p_value: *mut ffi::sqlite3_value = ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_preupdate_old(self.db, i, &mut p_value) };
+        PreUpdateCase::value(rc, p_value)
+    }
+
+    /// The value of the `i`th column of the row after the change.
+    ///
+    /// Fails with `SQLITE_MISUSE` for a delete and `SQLITE_RANGE` if `i` is
+    /// out of bounds.
+    pub fn new(&self, i: i32) -> Result<ValueRef> {
+        let mut p_value: *mut ffi::sqlite3_value = ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_preupdate_new(self.db, i, &mut p_value) };
+        PreUpdateCase::value(rc, p_value)
+    }
+
+    fn value<'a>(rc: c_int, p_value: *mut ffi::sqlite3_value) -> Result<ValueRef<'a>> {
+        if rc != ffi::SQLITE_OK {
+            return Err(error_from_sqlite_code(rc, None));
+        }
+        if p_value.is_null() {
+            Ok(ValueRef::Null)
+        } else {
+            Ok(unsafe { ValueRef::from_value(p_value) })
+        }
+    }
+}
+
+impl Connection {
+    /// Register a callback function to be invoked before each row is updated,
+    /// inserted or deleted in a rowid table.
+    ///
+    /// The callback parameters are:
+    ///
+    /// - the type of database update (`Insert`, `Update` or `Delete`),
+    /// - the name of the database ("main", "temp", ...),
+    /// - the name of the table that is updated,
+    /// - the old and new column values of the row (see `PreUpdateCase`).
+    ///
+    /// SQLite has a single pre-update hook per connection, which is also used
+    /// by the `session` extension: registering a callback here and recording
+    /// changes with a `Session` on the same connection conflict.
+    pub fn preupdate_hook<F>(&self, hook: Option<F>)
+        where F: FnMut(Action, &str, &str, &PreUpdateCase) + Send + 'static
+    {
+        self.db.borrow_mut().preupdate_hook(hook);
+    }
+}
+
+impl InnerConnection {
+    fn preupdate_hook<F>(&mut self, hook: Option<F>)
+        where F: FnMut(Action, &str, &str, &PreUpdateCase) + Send + 'static
+    {
+        unsafe extern "C" fn call_boxed_closure(p_arg: *mut c_void,
+                                                sqlite: *mut ffi::sqlite3,
+                                                action_code: c_int,
+                                                db_str: *const c_char,
+                                                tbl_str: *const c_char,
+                                                old_row_id: i64,
+                                                new_row_id: i64) {
+            let boxed_hook = p_arg as *mut Box<FnMut(Action, &str, &str, &PreUpdateCase) + Send>;
+
+            let action = Action::from(action_code);
+            let db_name = String::from_utf8_lossy(CStr::from_ptr(db_str).to_bytes());
+            let tbl_name = String::from_utf8_lossy(CStr::from_ptr(tbl_str).to_bytes());
+            let case = PreUpdateCase {
+                db: sqlite,
+                action: action,
+                old_row_id: old_row_id,
+                new_row_id: new_row_id,
+            };
+
+            let _ = catch_unwind(AssertUnwindSafe(|| {
+                (*boxed_hook)(action, &db_name, &tbl_name, &case)
+            }));
+        }
+
+        let boxed_hook = hook.map(|f| {
+            let hook: Box<FnMut(Action, &str, &str, &PreUpdateCase) + Send> = Box::new(f);
+            Box::new(hook)
+        });
+        match boxed_hook {
+            Some(ref hook) => {
+                let p_arg = &**hook as
+                            *const Box<FnMut(Action, &str, &str, &PreUpdateCase) + Send> as
+                            *mut c_void;
+                unsafe { ffi::sqlite3_preupdate_hook(self.db(), Some(call_boxed_closure), p_arg) };
+            }
+            None => unsafe {
+                ffi::sqlite3_preupdate_hook(self.db(), None, ptr::null_mut());
+            },
+        }
+        self.preupdate_hook = boxed_hook;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use hooks::{Action, PreUpdateCase};
+    use types::{Value, ValueRef};
+    use Connection;
+
+    #[test]
+    fn test_preupdate_hook_values() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo (t TEXT, i INTEGER);
+                          INSERT INTO foo VALUES ('old', 1);")
+            .unwrap();
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let hook_changes = changes.clone();
+        db.preupdate_hook(Some(move |action: Action, db: &str, tbl: &str, case: &PreUpdateCase| {
+            assert_eq!("main", db);
+            assert_eq!("foo", tbl);
+            assert_eq!(2, case.count());
+            assert_eq!(0, case.depth());
+            let old = case.old(0).ok().map(Value::from);
+            let new = case.new(0).ok().map(Value::from);
+            hook_changes
+                .lock()
+                .unwrap()
+                .push((action, case.old_row_id(), case.new_row_id(), old, new));
+        }));
+
+        db.execute_batch("INSERT INTO foo VALUES ('new', 2);
+                          UPDATE foo SET t = 'updated' WHERE i = 1;
+                          DELETE FROM foo WHERE i = 2;")
+            .unwrap();
+
+        let changes = changes.lock().unwrap();
+        assert_eq!(*changes,
+                   vec![(Action::Insert, None, Some(2), None, Some(Value::Text("new".to_owned()))),
+                        (Action::Update,
+                         Some(1),
+                         Some(1),
+                         Some(Value::Text("old".to_owned())),
+                         Some(Value::Text("updated".to_owned()))),
+                        (Action::Delete, Some(2), None, Some(Value::Text("new".to_owned())), None)]);
+    }
+
+    #[test]
+    fn test_preupdate_hook_depth_and_range() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo (x INTEGER);
+                          CREATE TABLE log (x INTEGER);
+                          CREATE TRIGGER foo_log AFTER INSERT ON foo BEGIN
+                              INSERT INTO log VALUES (new.x);
+                          END;")
+            .unwrap();
+
+        let depths = Arc::new(Mutex::new(Vec::new()));
+        let hook_depths = depths.clone();
+        db.preupdate_hook(Some(move |_: Action, _: &str, tbl: &str, case: &PreUpdateCase| {
+            assert!(case.new(1).is_err());
+            assert!(case.old(0).is_err());
+            if let Ok(ValueRef::Integer(x)) = case.new(0) {
+                hook_depths.lock().unwrap().push((tbl.to_owned(), case.depth(), x));
+            }
+        }));
+        db.execute("INSERT INTO foo VALUES (42)", &[]).unwrap();
+
+        assert_eq!(*depths.lock().unwrap(),
+                   vec![("foo".to_owned(), 0, 42), ("log".to_owned(), 1, 42)]);
+
+        db.preupdate_hook(None::<fn(Action, &str, &str, &PreUpdateCase)>);
+        db.execute("INSERT INTO foo VALUES (1)", &[]).unwrap();
+        assert_eq!(2, depths.lock().unwrap().len());
+    }
+}
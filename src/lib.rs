@@ -2,6 +2,7 @@
 //! an interface similar to [rust-postgres](https://github.com/sfackler/rust-postgres).
 //!
 //! ```rust
+//! #[macro_use]
 //! extern crate rusqlite;
 //! extern crate time;
 //!
@@ -33,7 +34,7 @@
 //!     };
 //!     conn.execute("INSERT INTO person (name, time_created, data)
 //!                   VALUES (?1, ?2, ?3)",
-//!                  &[&me.name, &me.time_created, &me.data]).unwrap();
+//!                  params![me.name, me.time_created, me.data]).unwrap();
 //!
 //!     let mut stmt = conn.prepare("SELECT id, name, time_created, data FROM person").unwrap();
 //!     let person_iter = stmt.query_map(&[], |row| {
@@ -59,6 +60,9 @@ extern crate bitflags;
 #[cfg(all(test, feature = "trace"))]
 #[macro_use]
 extern crate lazy_static;
+#[cfg(all(test, feature = "serde"))]
+#[macro_use]
+extern crate serde_derive;
 
 use std::default::Default;
 use std::convert;
@@ -70,7 +74,7 @@ use std::cell::RefCell;
 use std::ffi::{CStr, CString};
 use std::result;
 use std::str;
-use std::sync::{Once, ONCE_INIT};
+use std::sync::{Arc, Mutex, Once, ONCE_INIT};
 use std::sync::atomic::{AtomicBool, ATOMIC_BOOL_INIT, Ordering};
 use std::os::raw::{c_int, c_char};
 
@@ -80,9 +84,13 @@ use raw_statement::RawStatement;
 use cache::StatementCache;
 
 pub use statement::Statement;
+pub use params::Params;
+pub use column::Column;
+#[cfg(feature = "column_metadata")]
+pub use column::ColumnMetadata;
 use statement::StatementCrateImpl;
 
-pub use row::{Row, Rows, MappedRows, AndThenRows, RowIndex};
+pub use row::{Row, Rows, MappedRows, AndThenRows, RowIndex, FromRow};
 use row::RowsCrateImpl;
 
 #[allow(deprecated)]
@@ -101,26 +109,45 @@ pub use version::*;
 #[allow(deprecated)]
 pub use load_extension_guard::{SqliteLoadExtensionGuard, LoadExtensionGuard};
 
+#[macro_use]
+mod params;
 pub mod types;
 mod version;
 mod transaction;
+mod busy;
 mod cache;
 mod error;
 mod raw_statement;
 mod row;
 mod statement;
+mod column;
+mod pragma;
+pub mod schema;
+pub mod migrations;
 #[cfg(feature = "load_extension")]
 mod load_extension_guard;
 #[cfg(feature = "trace")]
 pub mod trace;
 #[cfg(feature = "backup")]
 pub mod backup;
+#[cfg(any(feature = "functions", feature = "vtab"))]
+mod context;
 #[cfg(feature = "functions")]
 pub mod functions;
 #[cfg(feature = "blob")]
 pub mod blob;
 #[cfg(feature = "limits")]
 pub mod limits;
+#[cfg(feature = "hooks")]
+pub mod hooks;
+#[cfg(feature = "collation")]
+mod collation;
+#[cfg(feature = "vtab")]
+pub mod vtab;
+#[cfg(feature = "session")]
+pub mod session;
+#[cfg(feature = "serde")]
+pub mod serde;
 
 // Number of cached prepared statements we'll hold on to.
 const STATEMENT_CACHE_DEFAULT_CAPACITY: usize = 16;
@@ -147,6 +174,7 @@ fn path_to_cstring(p: &Path) -> Result<CString> {
 }
 
 /// Name for a database within a SQLite connection.
+#[derive(Clone, Copy, Debug)]
 pub enum DatabaseName<'a> {
     /// The main database.
     Main,
@@ -158,9 +186,10 @@ pub enum DatabaseName<'a> {
     Attached(&'a str),
 }
 
-// Currently DatabaseName is only used by the backup and blob mods, so hide this (private)
-// impl to avoid dead code warnings.
-#[cfg(any(feature = "backup", feature = "blob"))]
+// Currently DatabaseName is only used by the backup, blob, column_metadata and session mods, so
+// hide this (private) impl to avoid dead code warnings.
+#[cfg(any(feature = "backup", feature = "blob", feature = "column_metadata",
+          feature = "session"))]
 impl<'a> DatabaseName<'a> {
     fn to_cstring(&self) -> Result<CString> {
         use self::DatabaseName::{Main, Temp, Attached};
@@ -279,7 +308,8 @@ impl Connection {
         self.db.borrow_mut().execute_batch(sql)
     }
 
-    /// Convenience method to prepare and execute a single SQL statement.
+    /// Convenience method to prepare and execute a single SQL statement with the parameters
+    /// `params` (see `Params` for the accepted types).
     ///
     /// On success, returns the number of rows that were changed or inserted or deleted (via
     /// `sqlite3_changes`).
@@ -287,45 +317,37 @@ impl Connection {
     /// ## Example
     ///
     /// ```rust,no_run
-    /// # use rusqlite::{Connection};
+    /// # use rusqlite::{Connection, Result};
     /// fn update_rows(conn: &Connection) {
     ///     match conn.execute("UPDATE foo SET bar = 'baz' WHERE qux = ?", &[&1i32]) {
     ///         Ok(updated) => println!("{} rows were updated", updated),
     ///         Err(err) => println!("update failed: {}", err),
     ///     }
     /// }
+    ///
+    /// fn insert(conn: &Connection) -> Result<i32> {
+    ///     conn.execute("INSERT INTO test (name) VALUES (:name)", &[(":name", &"one")])
+    /// }
     /// ```
     ///
     /// # Failure
     ///
     /// Will return `Err` if `sql` cannot be converted to a C-compatible string or if the
     /// underlying SQLite call fails.
-    pub fn execute(&self, sql: &str, params: &[&ToSql]) -> Result<c_int> {
+    pub fn execute<P: Params>(&self, sql: &str, params: P) -> Result<c_int> {
         self.prepare(sql)
             .and_then(|mut stmt| stmt.execute(params))
     }
 
     /// Convenience method to prepare and execute a single SQL statement with named parameter(s).
     ///
-    /// On success, returns the number of rows that were changed or inserted or deleted (via
-    /// `sqlite3_changes`).
-    ///
-    /// ## Example
-    ///
-    /// ```rust,no_run
-    /// # use rusqlite::{Connection, Result};
-    /// fn insert(conn: &Connection) -> Result<i32> {
-    ///     conn.execute_named("INSERT INTO test (name) VALUES (:name)", &[(":name", &"one")])
-    /// }
-    /// ```
-    ///
-    /// # Failure
+    /// ## Deprecated
     ///
-    /// Will return `Err` if `sql` cannot be converted to a C-compatible string or if the
-    /// underlying SQLite call fails.
+    /// `execute` now accepts named parameters.
+    #[deprecated(since = "0.14.0", note = "Use execute instead")]
     pub fn execute_named(&self, sql: &str, params: &[(&str, &ToSql)]) -> Result<c_int> {
         self.prepare(sql)
-            .and_then(|mut stmt| stmt.execute_named(params))
+            .and_then(|mut stmt| stmt.execute(params))
     }
 
     /// Get the SQLite rowid of the most recent successful INSERT.
@@ -355,8 +377,9 @@ impl Connection {
     ///
     /// Will return `Err` if `sql` cannot be converted to a C-compatible string or if the
     /// underlying SQLite call fails.
-    pub fn query_row<T, F>(&self, sql: &str, params: &[&ToSql], f: F) -> Result<T>
-        where F: FnOnce(&Row) -> T
+    pub fn query_row<T, P, F>(&self, sql: &str, params: P, f: F) -> Result<T>
+        where P: Params,
+              F: FnOnce(&Row) -> T
     {
         let mut stmt = try!(self.prepare(sql));
         stmt.query_row(params, f)
@@ -365,19 +388,14 @@ impl Connection {
     /// Convenience method to execute a query with named parameter(s) that is expected to return
     /// a single row.
     ///
-    /// If the query returns more than one row, all rows except the first are ignored.
-    ///
-    /// # Failure
+    /// ## Deprecated
     ///
-    /// Will return `Err` if `sql` cannot be converted to a C-compatible string or if the
-    /// underlying SQLite call fails.
+    /// `query_row` now accepts named parameters.
+    #[deprecated(since = "0.14.0", note = "Use query_row instead")]
     pub fn query_row_named<T, F>(&self, sql: &str, params: &[(&str, &ToSql)], f: F) -> Result<T>
         where F: FnOnce(&Row) -> T
     {
-        let mut stmt = try!(self.prepare(sql));
-        let mut rows = try!(stmt.query_named(params));
-
-        rows.get_expected_row().map(|r| f(&r))
+        self.query_row(sql, params, f)
     }
 
     /// Convenience method to execute a query that is expected to return a single row,
@@ -403,12 +421,13 @@ impl Connection {
     ///
     /// Will return `Err` if `sql` cannot be converted to a C-compatible string or if the
     /// underlying SQLite call fails.
-    pub fn query_row_and_then<T, E, F>(&self,
-                                       sql: &str,
-                                       params: &[&ToSql],
-                                       f: F)
-                                       -> result::Result<T, E>
-        where F: FnOnce(&Row) -> result::Result<T, E>,
+    pub fn query_row_and_then<T, E, P, F>(&self,
+                                          sql: &str,
+                                          params: P,
+                                          f: F)
+                                          -> result::Result<T, E>
+        where P: Params,
+              F: FnOnce(&Row) -> result::Result<T, E>,
               E: convert::From<Error>
     {
         let mut stmt = try!(self.prepare(sql));
@@ -561,6 +580,23 @@ impl Connection {
         self.db.borrow().db()
     }
 
+    /// Get access to a handle that can be used to interrupt long running queries from another
+    /// thread.
+    pub fn get_interrupt_handle(&self) -> InterruptHandle {
+        self.db.borrow().get_interrupt_handle()
+    }
+
+    /// Wraps a database handle owned by SQLite or by another `Connection`. The handle is not
+    /// closed when the returned `Connection` is dropped.
+    #[cfg(feature = "collation")]
+    unsafe fn from_handle(db: *mut ffi::sqlite3) -> Connection {
+        Connection {
+            db: RefCell::new(InnerConnection::new(db, false)),
+            cache: StatementCache::with_capacity(STATEMENT_CACHE_DEFAULT_CAPACITY),
+            path: None,
+        }
+    }
+
     fn decode_result(&self, code: c_int) -> Result<()> {
         self.db.borrow_mut().decode_result(code)
     }
@@ -580,6 +616,30 @@ impl fmt::Debug for Connection {
 
 struct InnerConnection {
     db: *mut ffi::sqlite3,
+    // It's unsafe to call `sqlite3_close` while another thread is performing a
+    // `sqlite3_interrupt`, and vice versa, so we take this mutex during those
+    // operations. It's null once the connection has been closed.
+    interrupt_lock: Arc<Mutex<*mut ffi::sqlite3>>,
+    // Whether we are responsible for closing `db`. Connections wrapping a handle owned elsewhere
+    // (e.g., the one passed to a `collation_needed` callback) must leave it open.
+    owned: bool,
+    // User-supplied busy handler, if any. SQLite holds a raw pointer to the inner box, so it must
+    // outlive its registration.
+    busy_handler: Option<Box<Box<FnMut(i32) -> bool + Send>>>,
+    #[cfg(feature = "hooks")]
+    commit_hook: Option<Box<Box<FnMut() -> bool + Send>>>,
+    #[cfg(feature = "hooks")]
+    rollback_hook: Option<Box<Box<FnMut() + Send>>>,
+    #[cfg(feature = "hooks")]
+    update_hook: Option<Box<Box<FnMut(hooks::Action, &str, &str, i64) + Send>>>,
+    #[cfg(feature = "hooks")]
+    progress_handler: Option<Box<Box<FnMut() -> bool + Send>>>,
+    #[cfg(feature = "hooks")]
+    authorizer: Option<Box<Box<FnMut(hooks::AuthContext) -> hooks::Authorization + Send>>>,
+    #[cfg(all(feature = "trace", any(feature = "bundled", feature = "modern_sqlite")))]
+    trace_v2: Option<Box<Box<FnMut(trace::TraceEvent) + Send>>>,
+    #[cfg(feature = "preupdate_hook")]
+    preupdate_hook: Option<Box<Box<FnMut(hooks::Action, &str, &str, &hooks::PreUpdateCase) + Send>>>,
 }
 
 /// Old name for `OpenFlags`. `SqliteOpenFlags` is deprecated.
@@ -738,6 +798,29 @@ To fix this, either:
 }
 
 impl InnerConnection {
+    fn new(db: *mut ffi::sqlite3, owned: bool) -> InnerConnection {
+        InnerConnection {
+            db: db,
+            interrupt_lock: Arc::new(Mutex::new(db)),
+            owned: owned,
+            busy_handler: None,
+            #[cfg(feature = "hooks")]
+            commit_hook: None,
+            #[cfg(feature = "hooks")]
+            rollback_hook: None,
+            #[cfg(feature = "hooks")]
+            update_hook: None,
+            #[cfg(feature = "hooks")]
+            progress_handler: None,
+            #[cfg(feature = "hooks")]
+            authorizer: None,
+            #[cfg(all(feature = "trace", any(feature = "bundled", feature = "modern_sqlite")))]
+            trace_v2: None,
+            #[cfg(feature = "preupdate_hook")]
+            preupdate_hook: None,
+        }
+    }
+
     fn open_with_flags(c_path: &CString, flags: OpenFlags) -> Result<InnerConnection> {
         ensure_valid_sqlite_version();
         ensure_safe_sqlite_threading_mode()?;
@@ -775,7 +858,7 @@ impl InnerConnection {
             // attempt to turn on extended results code; don't fail if we can't.
             ffi::sqlite3_extended_result_codes(db, 1);
 
-            Ok(InnerConnection { db: db })
+            Ok(InnerConnection::new(db, true))
         }
     }
 
@@ -783,6 +866,10 @@ impl InnerConnection {
         self.db
     }
 
+    fn get_interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle { db_lock: Arc::clone(&self.interrupt_lock) }
+    }
+
     fn decode_result(&mut self, code: c_int) -> Result<()> {
         if code == ffi::SQLITE_OK {
             Ok(())
@@ -792,13 +879,41 @@ impl InnerConnection {
     }
 
     fn close(&mut self) -> Result<()> {
+        if self.db.is_null() {
+            return Ok(());
+        }
+        let mut shared_handle = self.interrupt_lock.lock().unwrap();
+        assert!(!shared_handle.is_null(),
+                "Bug: Somehow interrupt_lock was cleared before the DB was closed");
+        if !self.owned {
+            // The handle outlives us, and SQLite may still call any handlers registered through
+            // this connection, so leak them instead of freeing them.
+            mem::forget(self.busy_handler.take());
+            #[cfg(feature = "hooks")]
+            {
+                mem::forget(self.commit_hook.take());
+                mem::forget(self.rollback_hook.take());
+                mem::forget(self.update_hook.take());
+                mem::forget(self.progress_handler.take());
+                mem::forget(self.authorizer.take());
+            }
+            #[cfg(all(feature = "trace", any(feature = "bundled", feature = "modern_sqlite")))]
+            mem::forget(self.trace_v2.take());
+            #[cfg(feature = "preupdate_hook")]
+            mem::forget(self.preupdate_hook.take());
+            *shared_handle = ptr::null_mut();
+            self.db = ptr::null_mut();
+            return Ok(());
+        }
         unsafe {
-            let r = ffi::sqlite3_close(self.db());
-            let r = self.decode_result(r);
-            if r.is_ok() {
+            let r = ffi::sqlite3_close(self.db);
+            if r == ffi::SQLITE_OK {
+                *shared_handle = ptr::null_mut();
                 self.db = ptr::null_mut();
+                Ok(())
+            } else {
+                Err(error_from_handle(self.db, r))
             }
-            r
         }
     }
 
@@ -886,6 +1001,33 @@ impl Drop for InnerConnection {
     }
 }
 
+/// Allows interrupting a long-running computation.
+pub struct InterruptHandle {
+    db_lock: Arc<Mutex<*mut ffi::sqlite3>>,
+}
+
+unsafe impl Send for InterruptHandle {}
+unsafe impl Sync for InterruptHandle {}
+
+impl InterruptHandle {
+    /// Interrupt the query currently executing on another thread. This will cause that query to
+    /// fail with a `SQLITE_INTERRUPT` error (`ErrorCode::OperationInterrupted`).
+    ///
+    /// If the connection has already been closed, this does nothing.
+    pub fn interrupt(&self) {
+        let db_handle = self.db_lock.lock().unwrap();
+        if !db_handle.is_null() {
+            unsafe { ffi::sqlite3_interrupt(*db_handle) }
+        }
+    }
+}
+
+impl fmt::Debug for InterruptHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InterruptHandle").finish()
+    }
+}
+
 /// Old name for `Statement`. `SqliteStatement` is deprecated.
 #[deprecated(since = "0.6.0", note = "Use Statement instead")]
 pub type SqliteStatement<'conn> = Statement<'conn>;
@@ -915,6 +1057,12 @@ mod test {
     #[allow(dead_code, unconditional_recursion)]
     fn ensure_send<T: Send>() {
         ensure_send::<Connection>();
+        ensure_send::<InterruptHandle>();
+    }
+
+    #[allow(dead_code, unconditional_recursion)]
+    fn ensure_sync<T: Sync>() {
+        ensure_sync::<InterruptHandle>();
     }
 
     pub fn checked_memory_handle() -> Connection {
@@ -1028,7 +1176,7 @@ mod test {
                        .unwrap());
 
         assert_eq!(3i32,
-                   db.query_row::<i32, _>("SELECT SUM(x) FROM foo", &[], |r| r.get(0))
+                   db.query_row::<i32, _, _>("SELECT SUM(x) FROM foo", &[], |r| r.get(0))
                        .unwrap());
     }
 
@@ -1146,7 +1294,7 @@ mod test {
         db.execute_batch(sql).unwrap();
 
         assert_eq!(10i64,
-                   db.query_row::<i64, _>("SELECT SUM(x) FROM foo", &[], |r| r.get(0))
+                   db.query_row::<i64, _, _>("SELECT SUM(x) FROM foo", &[], |r| r.get(0))
                    .unwrap());
 
         let result: Result<i64> = db.query_row("SELECT x FROM foo WHERE x > 5", &[], |r| r.get(0));
@@ -1221,6 +1369,57 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_interrupt() {
+        use std::sync::mpsc;
+        use std::thread;
+        use std::time::Duration;
+
+        let db = checked_memory_handle();
+        let interrupt_handle = db.get_interrupt_handle();
+
+        let (tx, rx) = mpsc::channel();
+        let worker = thread::spawn(move || {
+            // This query never finishes on its own.
+            let result = db.query_row("WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 \
+                                       FROM c) SELECT COUNT(*) FROM c",
+                                      &[],
+                                      |r| r.get::<_, i64>(0));
+            tx.send(()).unwrap();
+            result
+        });
+
+        // Keep interrupting until the worker gives up, since we can't know exactly when the
+        // query starts running.
+        while rx.try_recv().is_err() {
+            interrupt_handle.interrupt();
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        match worker.join().unwrap().unwrap_err() {
+            Error::SqliteFailure(err, _) => {
+                assert_eq!(err.code, ErrorCode::OperationInterrupted);
+            }
+            err => panic!("Unexpected error {}", err),
+        }
+    }
+
+    #[test]
+    fn test_interrupt_close() {
+        let db = checked_memory_handle();
+        let handle = db.get_interrupt_handle();
+        db.close().unwrap();
+        assert!(handle.db_lock.lock().unwrap().is_null());
+        // Interrupting a closed connection is a no-op.
+        handle.interrupt();
+
+        let db = checked_memory_handle();
+        let handle = db.get_interrupt_handle();
+        drop(db);
+        assert!(handle.db_lock.lock().unwrap().is_null());
+        handle.interrupt();
+    }
+
     #[test]
     fn test_version_string() {
         let n = version_number();
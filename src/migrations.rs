@@ -0,0 +1,364 @@
+//! Schema migrations tracked with `PRAGMA user_version`
+//!
+//! The version of a database is the number of migrations applied to it.
+//! Migrations are applied (or reverted) in a single transaction, which also
+//! updates `user_version`.
+//!
+//! ```rust
+//! # use rusqlite::{Connection, Result};
+//! # use rusqlite::migrations::{Migration, Migrations};
+//! fn migrate(conn: &mut Connection) -> Result<()> {
+//!     let migrations = Migrations::new()
+//!         .add(Migration::up("CREATE TABLE friend (name TEXT NOT NULL)")
+//!                  .down("DROP TABLE friend"))
+//!         .add(Migration::up("ALTER TABLE friend ADD COLUMN email TEXT"));
+//!     migrations.to_latest(conn)
+//! }
+//! ```
+
+use std::fmt;
+
+use schema::Table;
+use {Connection, DatabaseName, Error, Result, Transaction, TransactionBehavior};
+
+enum Step {
+    Sql(String),
+    Fn(Box<Fn(&Transaction) -> Result<()>>),
+}
+
+impl Step {
+    fn run(&self, tx: &Transaction) -> Result<()> {
+        match *self {
+            Step::Sql(ref sql) => tx.execute_batch(sql),
+            Step::Fn(ref f) => f(tx),
+        }
+    }
+}
+
+impl fmt::Debug for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Step::Sql(ref sql) => f.debug_tuple("Sql").field(sql).finish(),
+            Step::Fn(_) => f.debug_tuple("Fn").finish(),
+        }
+    }
+}
+
+/// A single migration: an up step, and optionally a down step reverting it.
+#[derive(Debug)]
+pub struct Migration {
+    up: Step,
+    down: Option<Step>,
+}
+
+impl Migration {
+    /// A migration running the SQL statements `sql`.
+    pub fn up(sql: &str) -> Migration {
+        Migration {
+            up: Step::Sql(sql.to_owned()),
+            down: None,
+        }
+    }
+
+    /// A migration calling `f`.
+    pub fn up_fn<F>(f: F) -> Migration
+        where F: Fn(&Transaction) -> Result<()> + 'static
+    {
+        Migration {
+            up: Step::Fn(Box::new(f)),
+            down: None,
+        }
+    }
+
+    /// Revert this migration by running the SQL statements `sql`.
+    pub fn down(mut self, sql: &str) -> Migration {
+        self.down = Some(Step::Sql(sql.to_owned()));
+        self
+    }
+
+    /// Revert this migration by calling `f`.
+    pub fn down_fn<F>(mut self, f: F) -> Migration
+        where F: Fn(&Transaction) -> Result<()> + 'static
+    {
+        self.down = Some(Step::Fn(Box::new(f)));
+        self
+    }
+}
+
+/// An ordered list of migrations.
+#[derive(Debug, Default)]
+pub struct Migrations {
+    migrations: Vec<Migration>,
+}
+
+impl Migrations {
+    pub fn new() -> Migrations {
+        Migrations::default()
+    }
+
+    /// Append `migration`, which brings the database to version `self.len() + 1`.
+    pub fn add(mut self, migration: Migration) -> Migrations {
+        self.migrations.push(migration);
+        self
+    }
+
+    /// The number of migrations, i.e. the latest version.
+    pub fn len(&self) -> usize {
+        self.migrations.len()
+    }
+
+    /// Returns `true` if there are no migrations.
+    pub fn is_empty(&self) -> bool {
+        self.migrations.is_empty()
+    }
+
+    /// The current version of the database (its `user_version`).
+    pub fn current_version(&self, conn: &Connection) -> Result<usize> {
+        let version: i64 = try!(conn.pragma_query_value(None, "user_version", |row| row.get(0)));
+        Ok(version as usize)
+    }
+
+    /// Apply all the migrations the database is missing.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if the database version is newer than the latest
+    /// migration, or if a migration fails. Nothing is applied in that case.
+    pub fn to_latest(&self, conn: &mut Connection) -> Result<()> {
+        let latest = self.len();
+        self.to_version(conn, latest)
+    }
+
+    /// Apply or revert migrations to bring the database to `version`.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `version` or the database version is newer than
+    /// the latest migration, if a migration to revert has no down step, or if
+    /// a migration fails. Nothing is applied in that case.
+    pub fn to_version(&self, conn: &mut Connection, version: usize) -> Result<()> {
+        // Take the write lock before reading the version, so that concurrent
+        // migrations of the same database cannot both apply the same steps.
+        let tx = try!(conn.transaction_with_behavior(TransactionBehavior::Immediate));
+        let current = try!(self.current_version(&tx));
+        if version > self.len() {
+            return Err(Error::MigrationError(format!("cannot migrate to version {}: there are only \
+                                                      {} migrations",
+                                                     version,
+                                                     self.len())));
+        }
+        if current > self.len() {
+            return Err(Error::MigrationError(format!("database version {} is newer than the {} \
+                                                      known migrations",
+                                                     current,
+                                                     self.len())));
+        }
+        if version == current {
+            return Ok(());
+        }
+
+        if version > current {
+            for migration in &self.migrations[current..version] {
+                try!(migration.up.run(&tx));
+            }
+        } else {
+            for (i, migration) in self.migrations[version..current].iter().enumerate().rev() {
+                match migration.down {
+                    Some(ref down) => try!(down.run(&tx)),
+                    None => {
+                        return Err(Error::MigrationError(format!("migration {} cannot be \
+                                                                  reverted",
+                                                                 version + i + 1)))
+                    }
+                }
+            }
+        }
+        try!(tx.pragma_update(None, "user_version", &(version as i64)));
+        tx.commit()
+    }
+
+    /// Check the migrations against an in-memory database: apply them all,
+    /// and compare the resulting schema with the one created by the SQL
+    /// statements `expected_schema`. If all the migrations can be reverted,
+    /// also check that reverting them leaves an empty schema.
+    ///
+    /// Schemas are compared through `Connection::tables` for the main
+    /// database, ignoring the SQL text of the tables, indexes and triggers.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if a migration fails or if the schemas differ.
+    pub fn validate(&self, expected_schema: &str) -> Result<()> {
+        let mut conn = try!(Connection::open_in_memory());
+        try!(self.to_latest(&mut conn));
+        let migrated = try!(tables(&conn));
+
+        let expected = try!(Connection::open_in_memory());
+        try!(expected.execute_batch(expected_schema));
+        let expected = try!(tables(&expected));
+
+        if migrated != expected {
+            return Err(Error::MigrationError(schema_difference(&migrated, &expected)));
+        }
+
+        if self.migrations.iter().all(|m| m.down.is_some()) {
+            try!(self.to_version(&mut conn, 0));
+            let reverted = try!(tables(&conn));
+            if !reverted.is_empty() {
+                return Err(Error::MigrationError(format!("tables left after reverting all \
+                                                          migrations: {:?}",
+                                                         reverted)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The tables of the main database, without their SQL text.
+fn tables(conn: &Connection) -> Result<Vec<Table>> {
+    let mut tables = try!(conn.tables(DatabaseName::Main));
+    for table in &mut tables {
+        table.sql = None;
+        for index in &mut table.indexes {
+            index.sql = None;
+        }
+        for trigger in &mut table.triggers {
+            trigger.sql = None;
+        }
+    }
+    Ok(tables)
+}
+
+fn schema_difference(migrated: &[Table], expected: &[Table]) -> String {
+    for table in expected {
+        match migrated.iter().find(|t| t.name == table.name) {
+            Some(t) if t == table => {}
+            Some(t) => {
+                return format!("table {} differs from the expected schema:\nmigrated: \
+                                {:?}\nexpected: {:?}",
+                               table.name,
+                               t,
+                               table)
+            }
+            None => return format!("table {} is missing from the migrated schema", table.name),
+        }
+    }
+    let extra: Vec<&str> = migrated
+        .iter()
+        .filter(|t| !expected.iter().any(|e| e.name == t.name))
+        .map(|t| t.name.as_str())
+        .collect();
+    format!("unexpected tables in the migrated schema: {}", extra.join(", "))
+}
+
+#[cfg(test)]
+mod test {
+    extern crate tempdir;
+    use self::tempdir::TempDir;
+    use std::time::Duration;
+
+    use super::{Migration, Migrations};
+    use {Connection, Error, ErrorCode, TransactionBehavior};
+
+    fn migrations() -> Migrations {
+        Migrations::new()
+            .add(Migration::up("CREATE TABLE friend (name TEXT NOT NULL)").down("DROP TABLE friend"))
+            .add(Migration::up_fn(|tx| {
+                                      tx.execute_batch("CREATE TABLE pet (name TEXT);
+                                                        INSERT INTO pet VALUES ('Rex');")
+                                  })
+                         .down_fn(|tx| tx.execute_batch("DROP TABLE pet")))
+            .add(Migration::up("CREATE INDEX friend_name ON friend (name)")
+                     .down("DROP INDEX friend_name"))
+    }
+
+    #[test]
+    fn test_to_latest_and_back() {
+        let mut db = Connection::open_in_memory().unwrap();
+        let migrations = migrations();
+        assert_eq!(0, migrations.current_version(&db).unwrap());
+
+        migrations.to_latest(&mut db).unwrap();
+        assert_eq!(3, migrations.current_version(&db).unwrap());
+        let pet: String = db.query_row("SELECT name FROM pet", &[], |r| r.get(0)).unwrap();
+        assert_eq!("Rex", pet);
+        // Already up to date
+        migrations.to_latest(&mut db).unwrap();
+
+        migrations.to_version(&mut db, 1).unwrap();
+        assert_eq!(1, migrations.current_version(&db).unwrap());
+        assert!(db.prepare("SELECT * FROM pet").is_err());
+        db.execute("INSERT INTO friend VALUES ('Bob')", &[]).unwrap();
+    }
+
+    #[test]
+    fn test_failed_migration_is_rolled_back() {
+        let mut db = Connection::open_in_memory().unwrap();
+        let migrations = migrations().add(Migration::up("CREATE TABLE broken (;"));
+        assert!(migrations.to_latest(&mut db).is_err());
+        assert_eq!(0, migrations.current_version(&db).unwrap());
+        assert!(db.prepare("SELECT * FROM friend").is_err());
+    }
+
+    #[test]
+    fn test_invalid_versions() {
+        let mut db = Connection::open_in_memory().unwrap();
+        let migrations = migrations().add(Migration::up("CREATE TABLE irreversible (x)"));
+        match migrations.to_version(&mut db, 5) {
+            Err(Error::MigrationError(_)) => (),
+            r => panic!("Unexpected result {:?}", r),
+        }
+        migrations.to_latest(&mut db).unwrap();
+        match migrations.to_version(&mut db, 0) {
+            Err(Error::MigrationError(msg)) => assert_eq!("migration 4 cannot be reverted", msg),
+            r => panic!("Unexpected result {:?}", r),
+        }
+        assert_eq!(4, migrations.current_version(&db).unwrap());
+
+        db.pragma_update(None, "user_version", &10).unwrap();
+        assert!(migrations.to_latest(&mut db).is_err());
+    }
+
+    #[test]
+    fn test_concurrent_migration() {
+        let temp_dir = TempDir::new("test_concurrent_migration").unwrap();
+        let path = temp_dir.path().join("test.db3");
+        let migrations = migrations();
+
+        // Another connection is migrating: the version must not be read
+        // before it is done.
+        let mut db1 = Connection::open(&path).unwrap();
+        db1.busy_timeout(Duration::from_millis(50)).unwrap();
+        let mut db2 = Connection::open(&path).unwrap();
+        let tx2 = db2.transaction_with_behavior(TransactionBehavior::Immediate).unwrap();
+        match migrations.to_latest(&mut db1) {
+            Err(Error::SqliteFailure(err, _)) => assert_eq!(ErrorCode::DatabaseBusy, err.code),
+            r => panic!("Unexpected result {:?}", r),
+        }
+        tx2.rollback().unwrap();
+
+        migrations.to_latest(&mut db1).unwrap();
+        migrations.to_latest(&mut db2).unwrap();
+        assert_eq!(3, migrations.current_version(&db2).unwrap());
+    }
+
+    #[test]
+    fn test_validate() {
+        let migrations = migrations();
+        migrations.validate("CREATE TABLE friend (name TEXT NOT NULL);
+                             CREATE TABLE pet (name TEXT);
+                             CREATE INDEX friend_name ON friend (name);")
+            .unwrap();
+
+        match migrations.validate("CREATE TABLE friend (name TEXT);
+                                   CREATE TABLE pet (name TEXT);
+                                   CREATE INDEX friend_name ON friend (name);") {
+            Err(Error::MigrationError(msg)) => assert!(msg.starts_with("table friend differs")),
+            r => panic!("Unexpected result {:?}", r),
+        }
+        assert!(migrations.validate("CREATE TABLE friend (name TEXT NOT NULL);").is_err());
+
+        let leaky = Migrations::new().add(Migration::up("CREATE TABLE a (x)").down("SELECT 1"));
+        assert!(leaky.validate("CREATE TABLE a (x)").is_err());
+    }
+}
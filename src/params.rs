@@ -0,0 +1,289 @@
+use {Result, Statement};
+use types::ToSql;
+
+/// Parameters which can be bound to a prepared statement.
+///
+/// `Statement::execute`, `Statement::query`, `Connection::execute` and the
+/// other methods running a statement accept any type implementing `Params`:
+///
+/// * `&[&ToSql]`, or an array of references to values of a single type like
+///   `&[&1i32, &2i32]`, for positional parameters. `&[]` binds no parameter.
+/// * `&[(&str, &ToSql)]`, or an array like `&[(":name", &"one")]`, for named
+///   parameters.
+/// * a tuple of values implementing `ToSql`, of up to 16 elements, like
+///   `(1i32, "one")`, and `()` for no parameter.
+/// * `Vec<Box<ToSql>>` when the parameters are only known at runtime.
+///
+/// The `params!` and `named_params!` macros build slices of values of
+/// different types:
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate rusqlite;
+/// use rusqlite::{Connection, Result};
+///
+/// fn insert(conn: &Connection, name: &str, age: i64) -> Result<()> {
+///     try!(conn.execute("INSERT INTO person (name, age) VALUES (?, ?)",
+///                       params![name, age]));
+///     try!(conn.execute("UPDATE person SET age = :age WHERE name = :name",
+///                       named_params!{":name": name, ":age": age + 1}));
+///     Ok(())
+/// }
+///
+/// fn main() {
+///     let conn = Connection::open_in_memory().unwrap();
+///     conn.execute_batch("CREATE TABLE person (name TEXT, age INTEGER)").unwrap();
+///     insert(&conn, "Steven", 42).unwrap();
+/// }
+/// ```
+///
+/// Unnamed parameters are bound in order, and their number must match the
+/// number of parameters of the statement. Named parameters not included are
+/// left to their most recently bound value, or `NULL` if they have never
+/// been bound.
+pub trait Params {
+    /// Bind the parameters to `stmt`.
+    fn bind_in(self, stmt: &mut Statement) -> Result<()>;
+}
+
+impl<'a> Params for &'a [&'a ToSql] {
+    fn bind_in(self, stmt: &mut Statement) -> Result<()> {
+        stmt.bind_parameters(self)
+    }
+}
+
+impl<'a> Params for &'a [(&'a str, &'a ToSql)] {
+    fn bind_in(self, stmt: &mut Statement) -> Result<()> {
+        stmt.bind_parameters_named(self)
+    }
+}
+
+// Only the positional empty array is covered, so that `&[]` can be inferred.
+impl<'a> Params for &'a [&'a ToSql; 0] {
+    fn bind_in(self, stmt: &mut Statement) -> Result<()> {
+        stmt.bind_parameters(self)
+    }
+}
+
+macro_rules! impl_for_array_ref {
+    ($($N:expr)+) => {
+        $(
+            impl<'a, T: ToSql + ?Sized> Params for &'a [&'a T; $N] {
+                fn bind_in(self, stmt: &mut Statement) -> Result<()> {
+                    stmt.bind_parameters(self)
+                }
+            }
+
+            impl<'a, T: ToSql + ?Sized> Params for &'a [(&'a str, &'a T); $N] {
+                fn bind_in(self, stmt: &mut Statement) -> Result<()> {
+                    stmt.bind_parameters_named(self)
+                }
+            }
+        )+
+    }
+}
+
+impl_for_array_ref!(1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16
+                    17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32);
+
+macro_rules! impl_for_tuple {
+    ($($field:tt $ftype:ident),*) => {
+        impl<$($ftype: ToSql),*> Params for ($($ftype,)*) {
+            #[allow(unused_variables)]
+            fn bind_in(self, stmt: &mut Statement) -> Result<()> {
+                stmt.bind_parameters(&[$(&self.$field as &ToSql),*] as &[&ToSql])
+            }
+        }
+    }
+}
+
+impl_for_tuple!();
+impl_for_tuple!(0 A);
+impl_for_tuple!(0 A, 1 B);
+impl_for_tuple!(0 A, 1 B, 2 C);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L, 12 M);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L, 12 M, 13 N);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L, 12 M, 13 N, 14 O);
+impl_for_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L, 12 M, 13 N, 14 O,
+                15 P);
+
+impl Params for Vec<Box<ToSql>> {
+    fn bind_in(self, stmt: &mut Statement) -> Result<()> {
+        stmt.bind_parameters(&self)
+    }
+}
+
+impl<'a> Params for &'a Vec<Box<ToSql>> {
+    fn bind_in(self, stmt: &mut Statement) -> Result<()> {
+        stmt.bind_parameters(self)
+    }
+}
+
+/// Build a `&[&ToSql]` slice of positional parameters from values of
+/// different types.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate rusqlite;
+/// use rusqlite::{Connection, Result};
+///
+/// fn add_person(conn: &Connection, name: &str, age: i64) -> Result<i32> {
+///     conn.execute("INSERT INTO person (name, age) VALUES (?, ?)", params![name, age])
+/// }
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! params {
+    () => {
+        &[] as &[&$crate::types::ToSql]
+    };
+    ($($param:expr),+ $(,)*) => {
+        &[$(&$param as &$crate::types::ToSql),+] as &[&$crate::types::ToSql]
+    };
+}
+
+/// Build a `&[(&str, &ToSql)]` slice of named parameters from values of
+/// different types.
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate rusqlite;
+/// use rusqlite::{Connection, Result};
+///
+/// fn add_person(conn: &Connection, name: &str, age: i64) -> Result<i32> {
+///     conn.execute("INSERT INTO person (name, age) VALUES (:name, :age)",
+///                  named_params!{":name": name, ":age": age})
+/// }
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! named_params {
+    () => {
+        &[] as &[(&str, &$crate::types::ToSql)]
+    };
+    ($($param_name:tt: $param_val:expr),+ $(,)*) => {
+        &[$(($param_name, &$param_val as &$crate::types::ToSql)),+]
+            as &[(&str, &$crate::types::ToSql)]
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use Connection;
+    use types::ToSql;
+
+    fn checked_memory_handle() -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo (i INTEGER, t TEXT, r REAL)").unwrap();
+        db
+    }
+
+    fn count(db: &Connection) -> i64 {
+        db.query_row("SELECT COUNT(*) FROM foo WHERE i = 1 AND t = 'one' AND r = 1.5",
+                     &[],
+                     |r| r.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_params_macros() {
+        let db = checked_memory_handle();
+        let t = "one".to_owned();
+        db.execute("INSERT INTO foo VALUES (?, ?, ?)", params![1i32, t, 1.5]).unwrap();
+        db.execute("INSERT INTO foo VALUES (:i, :t, :r)",
+                     named_params!{":i": 1i32, ":t": "one", ":r": 1.5,})
+            .unwrap();
+        assert_eq!(2, count(&db));
+
+        let mut stmt = db.prepare("SELECT COUNT(*) FROM foo").unwrap();
+        assert_eq!(2i64, stmt.query_row(params![], |r| r.get::<_, i64>(0)).unwrap());
+        assert_eq!(2i64, stmt.query_row(named_params!{}, |r| r.get::<_, i64>(0)).unwrap());
+    }
+
+    #[test]
+    fn test_tuples_and_arrays() {
+        let db = checked_memory_handle();
+        db.execute("INSERT INTO foo VALUES (?, ?, ?)", (1i32, "one", 1.5)).unwrap();
+        db.execute("INSERT INTO foo VALUES (?, 'one', ?)", &[&1.0, &1.5]).unwrap();
+        db.execute("INSERT INTO foo VALUES (:i, 'one', :r)", &[(":i", &1.0), (":r", &1.5)])
+            .unwrap();
+        db.execute("INSERT INTO foo VALUES (?, 'one', 1.5)", (1i32,)).unwrap();
+        assert_eq!(4, count(&db));
+
+        let mut stmt = db.prepare("SELECT COUNT(*) FROM foo").unwrap();
+        assert_eq!(4i64, stmt.query_row((), |r| r.get::<_, i64>(0)).unwrap());
+        assert_eq!(4i64, stmt.query_row(&[], |r| r.get::<_, i64>(0)).unwrap());
+    }
+
+    #[test]
+    fn test_named_slices() {
+        let db = checked_memory_handle();
+        db.execute("INSERT INTO foo VALUES (:i, :t, 1.5)",
+                     &[(":i", &1i32 as &ToSql), (":t", &"one")])
+            .unwrap();
+        let mut stmt = db.prepare("INSERT INTO foo VALUES (1, :t, :r)").unwrap();
+        stmt.execute(&[(":t", &"one" as &ToSql), (":r", &1.5)]).unwrap();
+        assert_eq!(2, count(&db));
+
+        let mut stmt = db.prepare("SELECT t FROM foo WHERE i = :i").unwrap();
+        assert_eq!("one",
+                   stmt.query_row(&[(":i", &1i32)], |r| r.get::<_, String>(0)).unwrap());
+        {
+            let mut rows = stmt.query(&[(":i", &1i32)]).unwrap();
+            assert_eq!("one", rows.next().unwrap().unwrap().get::<_, String>(0));
+        }
+        assert_eq!(2,
+                   stmt.query_map(&[(":i", &1i32)], |r| r.get::<_, String>(0))
+                       .unwrap()
+                       .count());
+        let rows: ::Result<Vec<String>> =
+            stmt.query_and_then(&[(":i", &1i32)], |r| r.get_checked(0)).unwrap().collect();
+        assert_eq!(vec!["one", "one"], rows.unwrap());
+    }
+
+    #[test]
+    fn test_unbound_named_parameters() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE test (x TEXT, y TEXT)").unwrap();
+
+        let mut stmt = db.prepare("INSERT INTO test (x, y) VALUES (:x, :y)").unwrap();
+        stmt.execute(&[(":x", &"one")]).unwrap();
+        stmt.execute(&[(":y", &"two")]).unwrap();
+
+        let mut stmt = db.prepare("SELECT x, y FROM test ORDER BY rowid").unwrap();
+        let rows: Vec<(Option<String>, Option<String>)> =
+            stmt.query_map(&[], |r| (r.get(0), r.get(1))).unwrap().map(|r| r.unwrap()).collect();
+        // `:y` is NULL on the first insert, and `:x` keeps its value on the second.
+        assert_eq!(vec![(Some("one".to_owned()), None),
+                        (Some("one".to_owned()), Some("two".to_owned()))],
+                   rows);
+    }
+
+    #[test]
+    fn test_dynamic_params() {
+        let db = checked_memory_handle();
+        let mut params: Vec<Box<ToSql>> = Vec::new();
+        params.push(Box::new(1i32));
+        params.push(Box::new("one".to_owned()));
+        params.push(Box::new(1.5));
+        db.execute("INSERT INTO foo VALUES (?, ?, ?)", &params).unwrap();
+        db.execute("INSERT INTO foo VALUES (?, ?, ?)", params).unwrap();
+        assert_eq!(2, count(&db));
+    }
+
+    #[test]
+    #[should_panic(expected = "incorrect number of parameters")]
+    fn test_wrong_parameter_count() {
+        let db = checked_memory_handle();
+        let _ = db.execute("INSERT INTO foo VALUES (?, ?, ?)", (1i32, "one"));
+    }
+}
@@ -1,8 +1,16 @@
 use std::ffi::CStr;
 use std::ptr;
-use std::os::raw::c_int;
+use std::os::raw::{c_char, c_int};
 use super::ffi;
 
+unsafe fn to_cstr<'a>(p: *const c_char) -> Option<&'a CStr> {
+    if p.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(p))
+    }
+}
+
 // Private newtype for raw sqlite3_stmts that finalize themselves when dropped.
 #[derive(Debug)]
 pub struct RawStatement(*mut ffi::sqlite3_stmt);
@@ -28,6 +36,25 @@ impl RawStatement {
         unsafe { CStr::from_ptr(ffi::sqlite3_column_name(self.0, idx)) }
     }
 
+    pub fn column_decltype(&self, idx: c_int) -> Option<&CStr> {
+        unsafe { to_cstr(ffi::sqlite3_column_decltype(self.0, idx)) }
+    }
+
+    #[cfg(feature = "column_metadata")]
+    pub fn column_database_name(&self, idx: c_int) -> Option<&CStr> {
+        unsafe { to_cstr(ffi::sqlite3_column_database_name(self.0, idx)) }
+    }
+
+    #[cfg(feature = "column_metadata")]
+    pub fn column_table_name(&self, idx: c_int) -> Option<&CStr> {
+        unsafe { to_cstr(ffi::sqlite3_column_table_name(self.0, idx)) }
+    }
+
+    #[cfg(feature = "column_metadata")]
+    pub fn column_origin_name(&self, idx: c_int) -> Option<&CStr> {
+        unsafe { to_cstr(ffi::sqlite3_column_origin_name(self.0, idx)) }
+    }
+
     pub fn step(&self) -> c_int {
         unsafe { ffi::sqlite3_step(self.0) }
     }
@@ -48,6 +75,10 @@ impl RawStatement {
         }
     }
 
+    pub fn bind_parameter_name(&self, index: c_int) -> Option<&CStr> {
+        unsafe { to_cstr(ffi::sqlite3_bind_parameter_name(self.0, index)) }
+    }
+
     pub fn clear_bindings(&self) -> c_int {
         unsafe { ffi::sqlite3_clear_bindings(self.0) }
     }
@@ -2,7 +2,7 @@ use std::{convert, result};
 use std::marker::PhantomData;
 
 use super::{Statement, Error, Result};
-use types::{FromSql, FromSqlError};
+use types::{FromSqlRef, FromSqlError, ValueRef};
 use statement::StatementCrateImpl;
 
 /// An handle for the resulting rows of a query.
@@ -143,12 +143,12 @@ impl<'stmt, T, E, F> Iterator for AndThenRows<'stmt, F>
 }
 
 /// A single result row of a query.
-pub struct Row<'a, 'stmt> {
+pub struct Row<'a, 'stmt: 'a> {
     stmt: &'stmt Statement<'stmt>,
     phantom: PhantomData<&'a ()>,
 }
 
-impl<'a, 'stmt> Row<'a, 'stmt> {
+impl<'a, 'stmt: 'a> Row<'a, 'stmt> {
     /// Get the value of a particular column of the result row.
     ///
     /// ## Failure
@@ -158,7 +158,7 @@ impl<'a, 'stmt> Row<'a, 'stmt> {
     ///    * If the underlying SQLite column type is not a valid type as a source for `T`
     ///    * If the underlying SQLite integral value is outside the range representable by `T`
     ///    * If `idx` is outside the range of columns in the returned query
-    pub fn get<I: RowIndex, T: FromSql>(&self, idx: I) -> T {
+    pub fn get<I: RowIndex, T: FromSqlRef<'a>>(&self, idx: I) -> T {
         self.get_checked(idx).unwrap()
     }
 
@@ -174,10 +174,10 @@ impl<'a, 'stmt> Row<'a, 'stmt> {
     ///
     /// Returns an `Error::InvalidColumnName` if `idx` is not a valid column name
     /// for this row.
-    pub fn get_checked<I: RowIndex, T: FromSql>(&self, idx: I) -> Result<T> {
+    pub fn get_checked<I: RowIndex, T: FromSqlRef<'a>>(&self, idx: I) -> Result<T> {
         let idx = try!(idx.idx(self.stmt));
         let value = self.stmt.value_ref(idx);
-        FromSql::column_result(value).map_err(|err| match err {
+        FromSqlRef::column_result_ref(value).map_err(|err| match err {
                                                   FromSqlError::InvalidType => {
                                                       Error::InvalidColumnType(idx,
                                                                                value.data_type())
@@ -191,12 +191,66 @@ impl<'a, 'stmt> Row<'a, 'stmt> {
                                               })
     }
 
+    /// Get the value of a particular column of the result row, borrowed from
+    /// the statement without any conversion.
+    ///
+    /// ## Failure
+    ///
+    /// Returns an `Error::InvalidColumnIndex` if `idx` is outside the valid column range
+    /// for this row.
+    ///
+    /// Returns an `Error::InvalidColumnName` if `idx` is not a valid column name
+    /// for this row.
+    pub fn get_ref<I: RowIndex>(&self, idx: I) -> Result<ValueRef<'a>> {
+        let idx = try!(idx.idx(self.stmt));
+        Ok(self.stmt.value_ref(idx))
+    }
+
     /// Return the number of columns in the current row.
     pub fn column_count(&self) -> i32 {
         self.stmt.column_count()
     }
 }
 
+impl<'a, 'stmt> AsRef<Statement<'stmt>> for Row<'a, 'stmt> {
+    fn as_ref(&self) -> &Statement<'stmt> {
+        self.stmt
+    }
+}
+
+/// A trait implemented by types that can be built from a result row.
+///
+/// It can be derived for structs with named fields with `#[derive(FromRow)]`
+/// from the `rusqlite-derive` crate, and used with `Statement::query_and_then`:
+///
+/// ```rust
+/// use rusqlite::{Connection, FromRow, Result, Row};
+///
+/// struct Person {
+///     id: i64,
+///     name: String,
+/// }
+///
+/// impl FromRow for Person {
+///     fn from_row(row: &Row) -> Result<Person> {
+///         Ok(Person {
+///                id: try!(row.get_checked("id")),
+///                name: try!(row.get_checked("name")),
+///            })
+///     }
+/// }
+///
+/// fn people(conn: &Connection) -> Result<Vec<Person>> {
+///     let mut stmt = try!(conn.prepare("SELECT id, name FROM person"));
+///     let rows = try!(stmt.query_and_then(&[], Person::from_row));
+///     rows.collect()
+/// }
+/// ```
+pub trait FromRow: Sized {
+    /// Build a value from the columns of `row`.
+    fn from_row(row: &Row) -> Result<Self>;
+}
+
 /// A trait implemented by types that can index into columns of a row.
 pub trait RowIndex {
     /// Returns the index of the appropriate column, or `None` if no such
@@ -0,0 +1,384 @@
+//! Schema introspection
+//!
+//! Describes the tables of a database, with their columns, indexes, foreign
+//! keys and triggers, from `sqlite_master` and the `table_info`,
+//! `index_list`, `index_info` and `foreign_key_list` pragmas.
+//!
+//! ```rust
+//! # use rusqlite::{Connection, DatabaseName, Result};
+//! fn print_schema(conn: &Connection) -> Result<()> {
+//!     for table in try!(conn.tables(DatabaseName::Main)) {
+//!         println!("{}", table.name);
+//!         for column in &table.columns {
+//!             println!("  {} {:?}", column.name, column.decl_type);
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use pragma::Sql;
+use {Connection, DatabaseName, Result};
+
+/// A table, as described by `sqlite_master` and `PRAGMA table_info`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Table {
+    /// Name of the table.
+    pub name: String,
+    /// The `CREATE TABLE` statement.
+    pub sql: Option<String>,
+    /// Columns, in declaration order.
+    pub columns: Vec<Column>,
+    /// Indexes, including the ones SQLite creates for `UNIQUE` and
+    /// `PRIMARY KEY` constraints, ordered by name.
+    pub indexes: Vec<Index>,
+    /// Foreign keys, in declaration order.
+    pub foreign_keys: Vec<ForeignKey>,
+    /// Triggers, ordered by name.
+    pub triggers: Vec<Trigger>,
+}
+
+/// A column of a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    /// Name of the column.
+    pub name: String,
+    /// Declared type, if any.
+    pub decl_type: Option<String>,
+    /// Whether the column has a `NOT NULL` constraint.
+    pub not_null: bool,
+    /// SQL text of the default value, if any.
+    pub default: Option<String>,
+    /// 1-based position of the column in the primary key, or `None` if it
+    /// isn't part of it.
+    pub primary_key: Option<u32>,
+}
+
+/// How an index was created.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexOrigin {
+    /// By a `CREATE INDEX` statement.
+    CreateIndex,
+    /// By a `UNIQUE` constraint.
+    Unique,
+    /// By a `PRIMARY KEY` constraint.
+    PrimaryKey,
+}
+
+/// An index on a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Index {
+    /// Name of the index.
+    pub name: String,
+    /// Whether the index is `UNIQUE`.
+    pub unique: bool,
+    /// How the index was created.
+    pub origin: IndexOrigin,
+    /// Whether the index is partial (has a `WHERE` clause).
+    pub partial: bool,
+    /// Indexed columns, in index order. `None` stands for an expression.
+    pub columns: Vec<Option<String>>,
+    /// The `CREATE INDEX` statement, `None` for indexes created by a
+    /// constraint.
+    pub sql: Option<String>,
+}
+
+/// A foreign key constraint of a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForeignKey {
+    /// The referenced table.
+    pub table: String,
+    /// Columns of the referencing table.
+    pub from: Vec<String>,
+    /// Columns of the referenced table. `None` means its primary key.
+    pub to: Vec<Option<String>>,
+    /// The `ON UPDATE` action (e.g. `"CASCADE"` or `"NO ACTION"`).
+    pub on_update: String,
+    /// The `ON DELETE` action.
+    pub on_delete: String,
+}
+
+/// A trigger on a table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trigger {
+    /// Name of the trigger.
+    pub name: String,
+    /// The `CREATE TRIGGER` statement.
+    pub sql: Option<String>,
+}
+
+impl Connection {
+    /// Describe all the tables of the database `db_name`, ordered by name.
+    /// SQLite's internal tables (`sqlite_%`) are left out.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `db_name` is not attached or if the underlying
+    /// SQLite calls fail.
+    pub fn tables(&self, db_name: DatabaseName) -> Result<Vec<Table>> {
+        let names = try!(self.schema_objects(db_name, "table", None));
+        let mut tables = Vec::with_capacity(names.len());
+        for (name, sql) in names {
+            tables.push(try!(self.describe_table(db_name, name, sql)));
+        }
+        Ok(tables)
+    }
+
+    /// Describe the table `table_name` of the database `db_name`, or return
+    /// `None` if there is no such table.
+    ///
+    /// # Failure
+    ///
+    /// Will return `Err` if `db_name` is not attached or if the underlying
+    /// SQLite calls fail.
+    pub fn table(&self, db_name: DatabaseName, table_name: &str) -> Result<Option<Table>> {
+        let mut tables = try!(self.schema_objects(db_name, "table", Some(table_name)));
+        match tables.pop() {
+            Some((name, sql)) => self.describe_table(db_name, name, sql).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Names and SQL of the schema objects of `type_` (on `table_name`).
+    fn schema_objects(&self,
+                      db_name: DatabaseName,
+                      type_: &str,
+                      table_name: Option<&str>)
+                      -> Result<Vec<(String, Option<String>)>> {
+        let mut sql = Sql::new();
+        sql.push_sql("SELECT name, sql FROM ");
+        sql.push_schema_name(db_name);
+        sql.push_sql(".sqlite_master WHERE type = ");
+        sql.push_string_literal(type_);
+        // Not `NOT LIKE 'sqlite_%'`: `_` is a wildcard and `LIKE` ignores case.
+        sql.push_sql(" AND substr(name, 1, 7) <> 'sqlite_'");
+        if let Some(table_name) = table_name {
+            sql.push_sql(" AND tbl_name = ");
+            sql.push_string_literal(table_name);
+        }
+        sql.push_sql(" ORDER BY name");
+
+        let mut stmt = try!(self.prepare(&sql));
+        let rows = try!(stmt.query_map(&[], |row| (row.get(0), row.get(1))));
+        rows.collect()
+    }
+
+    fn describe_table(&self,
+                      db_name: DatabaseName,
+                      name: String,
+                      sql: Option<String>)
+                      -> Result<Table> {
+        let mut columns = Vec::new();
+        try!(self.pragma(Some(db_name), "table_info", &name, |row| {
+            let decl_type: String = try!(row.get_checked(2));
+            let primary_key: u32 = try!(row.get_checked(5));
+            columns.push(Column {
+                             name: try!(row.get_checked(1)),
+                             decl_type: if decl_type.is_empty() {
+                                 None
+                             } else {
+                                 Some(decl_type)
+                             },
+                             not_null: try!(row.get_checked(3)),
+                             default: try!(row.get_checked(4)),
+                             primary_key: if primary_key == 0 {
+                                 None
+                             } else {
+                                 Some(primary_key)
+                             },
+                         });
+            Ok(())
+        }));
+
+        let index_sql = try!(self.schema_objects(db_name, "index", Some(&name)));
+        let mut indexes = Vec::new();
+        try!(self.pragma(Some(db_name), "index_list", &name, |row| {
+            let origin: String = try!(row.get_checked(3));
+            let name: String = try!(row.get_checked(1));
+            let sql = index_sql
+                .iter()
+                .find(|&&(ref index, _)| *index == name)
+                .and_then(|&(_, ref sql)| sql.clone());
+            indexes.push(Index {
+                             name: name,
+                             unique: try!(row.get_checked(2)),
+                             origin: match origin.as_str() {
+                                 "u" => IndexOrigin::Unique,
+                                 "pk" => IndexOrigin::PrimaryKey,
+                                 _ => IndexOrigin::CreateIndex,
+                             },
+                             partial: try!(row.get_checked(4)),
+                             columns: Vec::new(),
+                             sql: sql,
+                         });
+            Ok(())
+        }));
+        indexes.sort_by(|a, b| a.name.cmp(&b.name));
+        for index in &mut indexes {
+            let columns = &mut index.columns;
+            try!(self.pragma(Some(db_name), "index_info", &index.name, |row| {
+                columns.push(try!(row.get_checked(2)));
+                Ok(())
+            }));
+        }
+
+        let mut foreign_keys: Vec<(i32, ForeignKey)> = Vec::new();
+        try!(self.pragma(Some(db_name), "foreign_key_list", &name, |row| {
+            let id: i32 = try!(row.get_checked(0));
+            let from = try!(row.get_checked(3));
+            let to = try!(row.get_checked(4));
+            if let Some(&mut (_, ref mut fk)) = foreign_keys.iter_mut().find(|&&mut (i, _)| i == id) {
+                fk.from.push(from);
+                fk.to.push(to);
+                return Ok(());
+            }
+            foreign_keys.push((id,
+                               ForeignKey {
+                                   table: try!(row.get_checked(2)),
+                                   from: vec![from],
+                                   to: vec![to],
+                                   on_update: try!(row.get_checked(5)),
+                                   on_delete: try!(row.get_checked(6)),
+                               }));
+            Ok(())
+        }));
+        // SQLite lists foreign keys in reverse declaration order.
+        foreign_keys.sort_by(|a, b| b.0.cmp(&a.0));
+
+        let triggers = try!(self.schema_objects(db_name, "trigger", Some(&name)));
+
+        Ok(Table {
+               name: name,
+               sql: sql,
+               columns: columns,
+               indexes: indexes,
+               foreign_keys: foreign_keys.into_iter().map(|(_, fk)| fk).collect(),
+               triggers: triggers
+                   .into_iter()
+                   .map(|(name, sql)| Trigger { name: name, sql: sql })
+                   .collect(),
+           })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Column, ForeignKey, IndexOrigin};
+    use {Connection, DatabaseName};
+
+    fn column(name: &str,
+              decl_type: Option<&str>,
+              not_null: bool,
+              default: Option<&str>,
+              primary_key: Option<u32>)
+              -> Column {
+        Column {
+            name: name.to_owned(),
+            decl_type: decl_type.map(|s| s.to_owned()),
+            not_null: not_null,
+            default: default.map(|s| s.to_owned()),
+            primary_key: primary_key,
+        }
+    }
+
+    #[test]
+    fn test_tables() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE artist (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);
+                          CREATE TABLE track (
+                              album TEXT, disc INTEGER DEFAULT 1, number INTEGER,
+                              artist_id REFERENCES artist ON DELETE CASCADE,
+                              PRIMARY KEY (album, disc, number));
+                          CREATE INDEX track_artist ON track (artist_id, lower(album));
+                          CREATE TRIGGER track_delete AFTER DELETE ON track BEGIN SELECT 1; END;")
+            .unwrap();
+
+        let tables = db.tables(DatabaseName::Main).unwrap();
+        assert_eq!(vec!["artist", "track"],
+                   tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>());
+
+        let artist = &tables[0];
+        assert_eq!(vec![column("id", Some("INTEGER"), false, None, Some(1)),
+                        column("name", Some("TEXT"), true, None, None)],
+                   artist.columns);
+        assert_eq!(1, artist.indexes.len());
+        assert_eq!(IndexOrigin::Unique, artist.indexes[0].origin);
+        assert!(artist.indexes[0].unique);
+        assert_eq!(vec![Some("name".to_owned())], artist.indexes[0].columns);
+        assert_eq!(None, artist.indexes[0].sql);
+        assert!(artist.foreign_keys.is_empty());
+        assert!(artist.triggers.is_empty());
+
+        let track = &tables[1];
+        assert_eq!(vec![column("album", Some("TEXT"), false, None, Some(1)),
+                        column("disc", Some("INTEGER"), false, Some("1"), Some(2)),
+                        column("number", Some("INTEGER"), false, None, Some(3)),
+                        column("artist_id", None, false, None, None)],
+                   track.columns);
+        assert_eq!(2, track.indexes.len());
+        assert_eq!(IndexOrigin::PrimaryKey, track.indexes[0].origin);
+        assert_eq!("track_artist", track.indexes[1].name);
+        assert_eq!(IndexOrigin::CreateIndex, track.indexes[1].origin);
+        assert!(!track.indexes[1].unique);
+        assert_eq!(vec![Some("artist_id".to_owned()), None], track.indexes[1].columns);
+        assert!(track.indexes[1].sql.is_some());
+        assert_eq!(vec![ForeignKey {
+                            table: "artist".to_owned(),
+                            from: vec!["artist_id".to_owned()],
+                            to: vec![None],
+                            on_update: "NO ACTION".to_owned(),
+                            on_delete: "CASCADE".to_owned(),
+                        }],
+                   track.foreign_keys);
+        assert_eq!(1, track.triggers.len());
+        assert_eq!("track_delete", track.triggers[0].name);
+    }
+
+    #[test]
+    fn test_tables_named_like_internal_ones() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE sqlites (x);
+                          CREATE TABLE SQLiteCache (id INTEGER PRIMARY KEY AUTOINCREMENT);
+                          CREATE INDEX sqlites_x ON sqlites (x);
+                          INSERT INTO SQLiteCache DEFAULT VALUES;")
+            .unwrap();
+
+        let tables = db.tables(DatabaseName::Main).unwrap();
+        assert_eq!(vec!["SQLiteCache", "sqlites"],
+                   tables.iter().map(|t| t.name.as_str()).collect::<Vec<_>>());
+        assert_eq!(vec!["sqlites_x"],
+                   tables[1].indexes.iter().map(|i| i.name.as_str()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_table_by_name() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE parent (a, b, PRIMARY KEY (a, b));
+                          CREATE TABLE child (x, y, FOREIGN KEY (x, y) REFERENCES parent (a, b));")
+            .unwrap();
+
+        let child = db.table(DatabaseName::Main, "child").unwrap().unwrap();
+        assert_eq!(1, child.foreign_keys.len());
+        assert_eq!(vec!["x", "y"], child.foreign_keys[0].from);
+        assert_eq!(vec![Some("a".to_owned()), Some("b".to_owned())],
+                   child.foreign_keys[0].to);
+        assert!(db.table(DatabaseName::Main, "missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_temp_and_attached() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TEMP TABLE scratch (x);
+                          ATTACH DATABASE ':memory:' AS \"my db\";
+                          CREATE TABLE \"my db\".other (y INTEGER NOT NULL);")
+            .unwrap();
+
+        assert!(db.tables(DatabaseName::Main).unwrap().is_empty());
+        let temp = db.tables(DatabaseName::Temp).unwrap();
+        assert_eq!(1, temp.len());
+        assert_eq!("scratch", temp[0].name);
+        let other = db.table(DatabaseName::Attached("my db"), "other").unwrap().unwrap();
+        assert_eq!(vec![column("y", Some("INTEGER"), true, None, None)], other.columns);
+        assert!(db.tables(DatabaseName::Attached("missing")).is_err());
+    }
+}
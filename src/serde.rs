@@ -0,0 +1,1122 @@
+//! Deserialize rows into any type implementing serde's `Deserialize`, and
+//! serialize named parameters from any type implementing `Serialize`
+//!
+//! Structs are filled by matching the name of each field with the name of a
+//! column, through `Statement::column_index`, so the order of the columns
+//! doesn't matter. Each value is converted with the `FromSql` implementation
+//! of the field type. Maps get all the columns by name, and tuples and
+//! sequences all the columns by index.
+//!
+//! ```rust
+//! # extern crate rusqlite;
+//! # #[macro_use]
+//! # extern crate serde_derive;
+//! use rusqlite::{Connection, Result};
+//!
+//! #[derive(Deserialize)]
+//! struct Person {
+//!     id: i64,
+//!     name: String,
+//!     email: Option<String>,
+//! }
+//!
+//! fn people(conn: &Connection) -> Result<Vec<Person>> {
+//!     let mut stmt = try!(conn.prepare("SELECT name, id FROM person"));
+//!     let rows = try!(stmt.query_as::<Person, _>(&[]));
+//!     rows.collect()
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! In the other direction, `to_params_named` turns a struct or a map into
+//! owned values bound to the `:name`, `@name` or `$name` parameter matching
+//! each field.
+extern crate serde;
+
+use std::{fmt, result};
+
+use self::serde::de::{self, DeserializeOwned, DeserializeSeed, Deserializer, IntoDeserializer,
+                      MapAccess, SeqAccess, Visitor};
+use self::serde::ser::{self, Impossible, Serialize, SerializeMap, SerializeStruct, Serializer};
+
+use {AndThenRows, Error, Params, Result, Row, Statement};
+use types::{FromSql, ToSql, ToSqlOutput, Type, Value};
+
+/// Deserialize the current row of a query into a `T`.
+///
+/// # Failure
+///
+/// Will return `Err` if a field has no matching column and no default value,
+/// or if a value cannot be converted to the type of its field, in which case
+/// the error is a `Error::FromSqlConversionFailure` naming the column.
+pub fn from_row<T: DeserializeOwned>(row: &Row) -> Result<T> {
+    T::deserialize(RowDeserializer { row: row })
+}
+
+impl<'conn> Statement<'conn> {
+    /// Execute the prepared statement, returning an iterator over its rows
+    /// deserialized into `T` with `from_row`.
+    ///
+    /// ## Failure
+    ///
+    /// Will return `Err` if binding parameters fails.
+    pub fn query_as<'a, T, P>(&'a mut self,
+                              params: P)
+                              -> Result<AndThenRows<'a, fn(&Row) -> Result<T>>>
+        where T: DeserializeOwned,
+              P: Params
+    {
+        self.query_and_then(params, from_row::<T> as fn(&Row) -> Result<T>)
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::SerdeError(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Error {
+        Error::InvalidColumnName(field.to_owned())
+    }
+}
+
+struct RowDeserializer<'r, 'a: 'r, 'stmt: 'a> {
+    row: &'r Row<'a, 'stmt>,
+}
+
+impl<'de, 'r, 'a, 'stmt> Deserializer<'de> for RowDeserializer<'r, 'a, 'stmt> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let columns = self.row
+            .as_ref()
+            .column_names()
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| (i as i32, name))
+            .collect();
+        visitor.visit_map(RowMapAccess {
+                              row: self.row,
+                              columns: columns,
+                              next: 0,
+                          })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self,
+                                           _name: &'static str,
+                                           fields: &'static [&'static str],
+                                           visitor: V)
+                                           -> Result<V::Value> {
+        // Fields without a matching column are left to their default value,
+        // or reported by serde as missing.
+        let stmt = self.row.as_ref();
+        let columns = fields
+            .iter()
+            .filter_map(|field| stmt.column_index(field).ok().map(|i| (i, *field)))
+            .collect();
+        visitor.visit_map(RowMapAccess {
+                              row: self.row,
+                              columns: columns,
+                              next: 0,
+                          })
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let columns = self.row.as_ref().column_names();
+        visitor.visit_seq(RowSeqAccess {
+                              row: self.row,
+                              columns: columns,
+                              next: 0,
+                          })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self,
+                                                 _name: &'static str,
+                                                 _len: usize,
+                                                 visitor: V)
+                                                 -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self,
+                                                   _name: &'static str,
+                                                   visitor: V)
+                                                   -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf
+        option unit unit_struct enum identifier ignored_any
+    }
+}
+
+struct RowMapAccess<'r, 'a: 'r, 'stmt: 'a> {
+    row: &'r Row<'a, 'stmt>,
+    columns: Vec<(i32, &'r str)>,
+    next: usize,
+}
+
+impl<'de, 'r, 'a, 'stmt> MapAccess<'de> for RowMapAccess<'r, 'a, 'stmt> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.columns.get(self.next) {
+            Some(&(_, name)) => seed.deserialize(name.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (idx, name) = self.columns[self.next];
+        self.next += 1;
+        seed.deserialize(ColumnDeserializer {
+                             row: self.row,
+                             idx: idx,
+                             name: name,
+                         })
+    }
+}
+
+struct RowSeqAccess<'r, 'a: 'r, 'stmt: 'a> {
+    row: &'r Row<'a, 'stmt>,
+    columns: Vec<&'r str>,
+    next: usize,
+}
+
+impl<'de, 'r, 'a, 'stmt> SeqAccess<'de> for RowSeqAccess<'r, 'a, 'stmt> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        let name = match self.columns.get(self.next) {
+            Some(name) => *name,
+            None => return Ok(None),
+        };
+        let idx = self.next as i32;
+        self.next += 1;
+        seed.deserialize(ColumnDeserializer {
+                             row: self.row,
+                             idx: idx,
+                             name: name,
+                         })
+            .map(Some)
+    }
+}
+
+struct ColumnDeserializer<'r, 'a: 'r, 'stmt: 'a> {
+    row: &'r Row<'a, 'stmt>,
+    idx: i32,
+    name: &'r str,
+}
+
+impl<'r, 'a, 'stmt> ColumnDeserializer<'r, 'a, 'stmt> {
+    fn get<T: FromSql>(&self) -> Result<T> {
+        self.row
+            .get_checked(self.idx)
+            .map_err(|err| self.conversion_error(err))
+    }
+
+    // Name the column in the errors caused by its value.
+    fn conversion_error(&self, err: Error) -> Error {
+        let (data_type, msg) = match err {
+            Error::InvalidColumnType(_, t) => {
+                let msg = format!("invalid column type {}", t);
+                (t, msg)
+            }
+            Error::IntegralValueOutOfRange(_, i) => {
+                (Type::Integer, format!("integer {} out of range", i))
+            }
+            Error::FromSqlConversionFailure(_, t, err) => (t, err.to_string()),
+            Error::SerdeError(msg) => {
+                let data_type = self.row
+                    .get_checked::<i32, Value>(self.idx)
+                    .map(|v| v.data_type())
+                    .unwrap_or(Type::Null);
+                (data_type, msg)
+            }
+            err => return err,
+        };
+        Error::FromSqlConversionFailure(self.idx as usize,
+                                        data_type,
+                                        format!("column {}: {}", self.name, msg).into())
+    }
+}
+
+impl<'de, 'r, 'a, 'stmt> Deserializer<'de> for ColumnDeserializer<'r, 'a, 'stmt> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let r = match try!(self.get::<Value>()) {
+            Value::Null => visitor.visit_unit(),
+            Value::Integer(i) => visitor.visit_i64(i),
+            Value::Real(f) => visitor.visit_f64(f),
+            Value::Text(s) => visitor.visit_string(s),
+            Value::Blob(b) => visitor.visit_byte_buf(b),
+        };
+        r.map_err(|err| self.conversion_error(err))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(try!(self.get()))
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8(try!(self.get()))
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i16(try!(self.get()))
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i32(try!(self.get()))
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i64(try!(self.get()))
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(try!(self.get()))
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u16(try!(self.get()))
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u32(try!(self.get()))
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let i: i64 = try!(self.get());
+        if i < 0 {
+            return Err(self.conversion_error(Error::IntegralValueOutOfRange(self.idx, i)));
+        }
+        visitor.visit_u64(i as u64)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f32(try!(self.get::<f64>()) as f32)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f64(try!(self.get()))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let s: String = try!(self.get());
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => {
+                Err(self.conversion_error(Error::SerdeError(format!("expected a single \
+                                                                     character, got {:?}",
+                                                                    s))))
+            }
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(try!(self.get()))
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(try!(self.get()))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match try!(self.get::<Value>()) {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self,
+                                                   _name: &'static str,
+                                                   visitor: V)
+                                                   -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Unit variants stored as text, e.g. `enum Color { Red, Green }`.
+    fn deserialize_enum<V: Visitor<'de>>(self,
+                                         _name: &'static str,
+                                         _variants: &'static [&'static str],
+                                         visitor: V)
+                                         -> Result<V::Value> {
+        let s: String = try!(self.get());
+        visitor
+            .visit_enum(s.into_deserializer())
+            .map_err(|err| self.conversion_error(err))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// Named parameters serialized by `to_params_named`, which can be passed to
+/// `Statement::execute` or any other method taking `Params`.
+///
+/// Each field is bound to the parameter with the same name prefixed by `:`,
+/// `@` or `$`, or to the parameter with exactly the same name if the field
+/// name has one of these prefixes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamsNamed {
+    params: Vec<(String, ToSqlOutput<'static>)>,
+    // Names of the parameters for `as_named_params`, with a prefix.
+    prefixed_names: Vec<String>,
+    ignore_unused: bool,
+}
+
+impl ParamsNamed {
+    /// Skip the fields which don't match any parameter of the statement,
+    /// instead of failing with `Error::InvalidParameterName`.
+    pub fn ignore_unused(mut self) -> ParamsNamed {
+        self.ignore_unused = true;
+        self
+    }
+
+    /// The parameters as a slice of names and values for the `..._named`
+    /// methods, like `Statement::execute_named`.
+    ///
+    /// A field whose name doesn't start with `:`, `@` or `$` is bound to the
+    /// `:name` parameter. Unlike passing the `ParamsNamed` itself as `Params`,
+    /// a statement parameter left without a value is not an error, and every
+    /// field must match a parameter.
+    ///
+    /// ```rust
+    /// # #![allow(deprecated)]
+    /// # extern crate rusqlite;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// use rusqlite::{Connection, Result};
+    /// use rusqlite::serde::to_params_named;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Rename<'a> {
+    ///     old: &'a str,
+    ///     new: &'a str,
+    /// }
+    ///
+    /// fn rename(conn: &Connection, names: &Rename) -> Result<i32> {
+    ///     let params = try!(to_params_named(names));
+    ///     conn.execute_named("UPDATE person SET name = :new WHERE name = :old",
+    ///                        &params.as_named_params())
+    /// }
+    /// # fn main() {}
+    /// ```
+    pub fn as_named_params(&self) -> Vec<(&str, &ToSql)> {
+        self.prefixed_names
+            .iter()
+            .zip(&self.params)
+            .map(|(name, &(_, ref value))| (name.as_str(), value as &ToSql))
+            .collect()
+    }
+}
+
+fn has_prefix(name: &str) -> bool {
+    name.starts_with(|c| c == ':' || c == '@' || c == '$')
+}
+
+/// Serialize a struct or a map into named parameters.
+///
+/// `ParamsNamed` implements `Params`, so it is passed to `execute`, `query`
+/// and the other methods generic over `Params`. `ParamsNamed::as_named_params`
+/// gives the slice taken by the `..._named` methods, like
+/// `Statement::execute_named`.
+///
+/// ```rust
+/// # extern crate rusqlite;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// use rusqlite::{Connection, Result};
+/// use rusqlite::serde::to_params_named;
+///
+/// #[derive(Serialize)]
+/// struct Person {
+///     name: String,
+///     email: Option<String>,
+/// }
+///
+/// fn insert(conn: &Connection, person: &Person) -> Result<i32> {
+///     let params = try!(to_params_named(person));
+///     conn.execute("INSERT INTO person (name, email) VALUES (:name, :email)", &params)
+/// }
+/// # fn main() {}
+/// ```
+///
+/// # Failure
+///
+/// Will return `Err` if `value` isn't a struct or a map with string keys, or
+/// if a field value cannot be stored in SQLite (e.g. a sequence). Binding
+/// the parameters fails if a parameter of the statement has no value, or if
+/// a field has no matching parameter and `ParamsNamed::ignore_unused` wasn't
+/// called.
+pub fn to_params_named<T: Serialize + ?Sized>(value: &T) -> Result<ParamsNamed> {
+    let mut params = Vec::new();
+    try!(value.serialize(ParamsSerializer { params: &mut params }));
+    let prefixed_names = params.iter()
+        .map(|&(ref name, _)| if has_prefix(name) {
+                 name.clone()
+             } else {
+                 format!(":{}", name)
+             })
+        .collect();
+    Ok(ParamsNamed {
+           params: params,
+           prefixed_names: prefixed_names,
+           ignore_unused: false,
+       })
+}
+
+fn named_parameter_index(stmt: &Statement, name: &str) -> Result<Option<i32>> {
+    if has_prefix(name) {
+        return stmt.parameter_index(name);
+    }
+    for prefix in &[":", "@", "$"] {
+        if let Some(i) = try!(stmt.parameter_index(&format!("{}{}", prefix, name))) {
+            return Ok(Some(i));
+        }
+    }
+    Ok(None)
+}
+
+impl<'a> Params for &'a ParamsNamed {
+    fn bind_in(self, stmt: &mut Statement) -> Result<()> {
+        let mut bound = vec![false; stmt.parameter_count() as usize];
+        for &(ref name, ref value) in &self.params {
+            match try!(named_parameter_index(stmt, name)) {
+                Some(i) => {
+                    try!(stmt.bind_parameter(value, i));
+                    bound[i as usize - 1] = true;
+                }
+                None if self.ignore_unused => {}
+                None => return Err(Error::InvalidParameterName(name.clone())),
+            }
+        }
+        if let Some(i) = bound.iter().position(|bound| !bound) {
+            let index = i as i32 + 1;
+            let name = stmt.parameter_name(index)
+                .map_or_else(|| format!("?{}", index), |name| name.to_owned());
+            return Err(Error::SerdeError(format!("no value for parameter {}", name)));
+        }
+        Ok(())
+    }
+}
+
+impl Params for ParamsNamed {
+    fn bind_in(self, stmt: &mut Statement) -> Result<()> {
+        (&self).bind_in(stmt)
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::SerdeError(msg.to_string())
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        match *self {
+            Value::Null => serializer.serialize_none(),
+            Value::Integer(i) => serializer.serialize_i64(i),
+            Value::Real(f) => serializer.serialize_f64(f),
+            Value::Text(ref s) => serializer.serialize_str(s),
+            Value::Blob(ref b) => serializer.serialize_bytes(b),
+        }
+    }
+}
+
+fn not_a_struct() -> Error {
+    Error::SerdeError("named parameters can only be serialized from a struct or a map".to_owned())
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::SerdeError(format!("{} cannot be stored in SQLite", what))
+}
+
+struct ParamsSerializer<'p> {
+    params: &'p mut Vec<(String, ToSqlOutput<'static>)>,
+}
+
+impl<'p> ParamsSerializer<'p> {
+    fn push<T: Serialize + ?Sized>(&mut self, name: String, value: &T) -> Result<()> {
+        let value = try!(value.serialize(ValueSerializer).map_err(|err| match err {
+            Error::SerdeError(msg) => Error::SerdeError(format!("field {}: {}", name, msg)),
+            err => err,
+        }));
+        self.params.push((name, ToSqlOutput::Owned(value)));
+        Ok(())
+    }
+}
+
+impl<'p> Serializer for ParamsSerializer<'p> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = ParamsMapSerializer<'p>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_char(self, _v: char) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_unit_variant(self,
+                              _name: &'static str,
+                              _variant_index: u32,
+                              _variant: &'static str)
+                              -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self,
+                                                       _name: &'static str,
+                                                       value: &T)
+                                                       -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self,
+                                                        _name: &'static str,
+                                                        _variant_index: u32,
+                                                        _variant: &'static str,
+                                                        _value: &T)
+                                                        -> Result<()> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_struct(self,
+                              _name: &'static str,
+                              _len: usize)
+                              -> Result<Self::SerializeTupleStruct> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_variant(self,
+                               _name: &'static str,
+                               _variant_index: u32,
+                               _variant: &'static str,
+                               _len: usize)
+                               -> Result<Self::SerializeTupleVariant> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(ParamsMapSerializer {
+               params: self,
+               name: None,
+           })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self,
+                                _name: &'static str,
+                                _variant_index: u32,
+                                _variant: &'static str,
+                                _len: usize)
+                                -> Result<Self::SerializeStructVariant> {
+        Err(not_a_struct())
+    }
+}
+
+impl<'p> SerializeStruct for ParamsSerializer<'p> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self,
+                                              key: &'static str,
+                                              value: &T)
+                                              -> Result<()> {
+        self.push(key.to_owned(), value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct ParamsMapSerializer<'p> {
+    params: ParamsSerializer<'p>,
+    name: Option<String>,
+}
+
+impl<'p> SerializeMap for ParamsMapSerializer<'p> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match try!(key.serialize(ValueSerializer)) {
+            Value::Text(name) => {
+                self.name = Some(name);
+                Ok(())
+            }
+            _ => Err(Error::SerdeError("parameter names must be strings".to_owned())),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let name = self.name
+            .take()
+            .expect("serialize_value called before serialize_key");
+        self.params.push(name, value)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Serialize a field into the value bound to its parameter.
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = Impossible<Value, Error>;
+    type SerializeTuple = Impossible<Value, Error>;
+    type SerializeTupleStruct = Impossible<Value, Error>;
+    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeMap = Impossible<Value, Error>;
+    type SerializeStruct = Impossible<Value, Error>;
+    type SerializeStructVariant = Impossible<Value, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value> {
+        Ok(Value::Integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value> {
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value> {
+        if v > i64::max_value() as u64 {
+            return Err(Error::SerdeError(format!("integer {} out of range", v)));
+        }
+        Ok(Value::Integer(v as i64))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value> {
+        Ok(Value::Real(v as f64))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value> {
+        Ok(Value::Real(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value> {
+        Ok(Value::Text(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value> {
+        Ok(Value::Text(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
+        Ok(Value::Blob(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(self,
+                              _name: &'static str,
+                              _variant_index: u32,
+                              variant: &'static str)
+                              -> Result<Value> {
+        Ok(Value::Text(variant.to_owned()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self,
+                                                       _name: &'static str,
+                                                       value: &T)
+                                                       -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self,
+                                                        _name: &'static str,
+                                                        _variant_index: u32,
+                                                        _variant: &'static str,
+                                                        _value: &T)
+                                                        -> Result<Value> {
+        Err(unsupported("an enum variant with data"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(unsupported("a sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(unsupported("a tuple"))
+    }
+
+    fn serialize_tuple_struct(self,
+                              _name: &'static str,
+                              _len: usize)
+                              -> Result<Self::SerializeTupleStruct> {
+        Err(unsupported("a tuple struct"))
+    }
+
+    fn serialize_tuple_variant(self,
+                               _name: &'static str,
+                               _variant_index: u32,
+                               _variant: &'static str,
+                               _len: usize)
+                               -> Result<Self::SerializeTupleVariant> {
+        Err(unsupported("an enum variant with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(unsupported("a map"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(unsupported("a struct"))
+    }
+
+    fn serialize_struct_variant(self,
+                                _name: &'static str,
+                                _variant_index: u32,
+                                _variant: &'static str,
+                                _len: usize)
+                                -> Result<Self::SerializeStructVariant> {
+        Err(unsupported("an enum variant with data"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use {Connection, Error};
+    use types::Value;
+    use super::{from_row, to_params_named};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Person {
+        id: i64,
+        name: String,
+        email: Option<String>,
+        #[serde(default)]
+        age: u8,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Color {
+        Red,
+        Green,
+    }
+
+    fn checked_memory_handle() -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE person (id INTEGER PRIMARY KEY, name TEXT NOT NULL, \
+                          email TEXT, color TEXT);
+                          INSERT INTO person VALUES (1, 'Alice', 'alice@example.com', 'Red');
+                          INSERT INTO person VALUES (2, 'Bob', NULL, 'Green');")
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_query_as_struct() {
+        let db = checked_memory_handle();
+        let mut stmt = db.prepare("SELECT name, email, id FROM person ORDER BY id").unwrap();
+        let people: Vec<Person> = stmt.query_as(&[]).unwrap().map(|r| r.unwrap()).collect();
+        assert_eq!(vec![Person {
+                            id: 1,
+                            name: "Alice".to_owned(),
+                            email: Some("alice@example.com".to_owned()),
+                            age: 0,
+                        },
+                        Person {
+                            id: 2,
+                            name: "Bob".to_owned(),
+                            email: None,
+                            age: 0,
+                        }],
+                   people);
+    }
+
+    #[test]
+    fn test_from_row_tuples_maps_and_enums() {
+        let db = checked_memory_handle();
+        let (id, color): (i64, Color) =
+            db.query_row("SELECT id, color FROM person WHERE id = 2", &[], |r| from_row(r))
+                .unwrap()
+                .unwrap();
+        assert_eq!((2, Color::Green), (id, color));
+
+        let map: HashMap<String, Option<i64>> =
+            db.query_row("SELECT id, email FROM person WHERE id = 2", &[], |r| from_row(r))
+                .unwrap()
+                .unwrap();
+        assert_eq!(Some(&Some(2)), map.get("id"));
+        assert_eq!(Some(&None), map.get("email"));
+    }
+
+    #[test]
+    fn test_from_row_errors() {
+        let db = checked_memory_handle();
+        let mut stmt = db.prepare("SELECT id, email FROM person").unwrap();
+        match stmt.query_as::<Person, _>(&[]).unwrap().next().unwrap() {
+            Err(Error::InvalidColumnName(name)) => assert_eq!("name", name),
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        let mut stmt = db.prepare("SELECT name AS id, name, email FROM person").unwrap();
+        match stmt.query_as::<Person, _>(&[]).unwrap().next().unwrap() {
+            Err(Error::FromSqlConversionFailure(0, _, err)) => {
+                assert!(err.to_string().starts_with("column id: "))
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        let mut stmt = db.prepare("SELECT name AS color FROM person").unwrap();
+        let r: ::Result<(Color,)> = stmt.query_row(&[], |r| from_row(r)).unwrap();
+        match r {
+            Err(Error::FromSqlConversionFailure(0, _, err)) => {
+                assert!(err.to_string().starts_with("column color: "))
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+
+    #[derive(Serialize)]
+    struct NewPerson<'a> {
+        name: &'a str,
+        email: Option<&'a str>,
+        color: Color,
+    }
+
+    fn count(db: &Connection, sql: &str) -> i64 {
+        db.query_row(sql, &[], |r| r.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_to_params_named() {
+        let db = checked_memory_handle();
+        let person = NewPerson {
+            name: "Carol",
+            email: None,
+            color: Color::Red,
+        };
+        let params = to_params_named(&person).unwrap();
+        db.execute("INSERT INTO person (name, email, color) VALUES (:name, @email, $color)",
+                     &params)
+            .unwrap();
+        assert_eq!(1,
+                   count(&db,
+                         "SELECT COUNT(*) FROM person WHERE name = 'Carol' AND email IS NULL \
+                          AND color = 'Red'"));
+
+        let mut map = HashMap::new();
+        map.insert(":name".to_owned(), Value::Text("Dave".to_owned()));
+        map.insert("id".to_owned(), Value::Integer(10));
+        db.execute("INSERT INTO person (id, name) VALUES (:id, :name)",
+                     to_params_named(&map).unwrap())
+            .unwrap();
+        assert_eq!(1, count(&db, "SELECT COUNT(*) FROM person WHERE id = 10 AND name = 'Dave'"));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_as_named_params() {
+        let db = checked_memory_handle();
+        let person = NewPerson {
+            name: "Carol",
+            email: None,
+            color: Color::Green,
+        };
+        let params = to_params_named(&person).unwrap();
+        db.execute_named("INSERT INTO person (name, email, color) VALUES (:name, :email, :color)",
+                           &params.as_named_params())
+            .unwrap();
+
+        let mut map = HashMap::new();
+        map.insert("@color".to_owned(), Value::Text("Green".to_owned()));
+        let params = to_params_named(&map).unwrap();
+        let mut stmt = db.prepare("SELECT name FROM person WHERE color = @color ORDER BY id")
+            .unwrap();
+        let names: Vec<String> = stmt.query_map_named(&params.as_named_params(), |r| r.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(vec!["Bob", "Carol"], names);
+    }
+
+    #[test]
+    fn test_to_params_named_errors() {
+        let db = checked_memory_handle();
+        let person = NewPerson {
+            name: "Carol",
+            email: Some("carol@example.com"),
+            color: Color::Green,
+        };
+        let params = to_params_named(&person).unwrap();
+        match db.execute("INSERT INTO person (name, email) VALUES (:name, :email)", &params) {
+            Err(Error::InvalidParameterName(name)) => assert_eq!("color", name),
+            r => panic!("Unexpected result {:?}", r),
+        }
+        match db.execute("INSERT INTO person (name, email, color, id) \
+                          VALUES (:name, :email, :color, :id)",
+                         &params) {
+            Err(Error::SerdeError(msg)) => assert_eq!("no value for parameter :id", msg),
+            r => panic!("Unexpected result {:?}", r),
+        }
+        db.execute("INSERT INTO person (name, email) VALUES (:name, :email)",
+                     params.ignore_unused())
+            .unwrap();
+        assert_eq!(1, count(&db, "SELECT COUNT(*) FROM person WHERE name = 'Carol'"));
+
+        assert!(to_params_named(&(1, 2)).is_err());
+        let mut map = HashMap::new();
+        map.insert("ids", vec![1, 2]);
+        match to_params_named(&map) {
+            Err(Error::SerdeError(msg)) => assert!(msg.starts_with("field ids: ")),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+}
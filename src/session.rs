@@ -0,0 +1,866 @@
+//! [Session Extension](https://sqlite.org/sessionintro.html)
+//!
+//! A `Session` records the changes made to the tables of a database and
+//! packages them as a `Changeset` (or a more compact patchset), which can then
+//! be applied to another database with `Connection::apply_changeset`.
+//!
+//! ```rust,no_run
+//! # use rusqlite::{Connection, Result};
+//! # use rusqlite::session::{ConflictAction, Session};
+//! fn sync(device: &Connection, central: &Connection) -> Result<()> {
+//!     let changeset = {
+//!         let mut session = try!(Session::new(device));
+//!         try!(session.attach(None));
+//!         try!(device.execute_batch("INSERT INTO notes VALUES ('offline edit')"));
+//!         try!(session.changeset())
+//!     };
+//!     // `changeset.as_bytes()` can be sent over the network and turned back into
+//!     // a `Changeset` with `Changeset::from`.
+//!     central.apply_changeset(&changeset,
+//!                             None::<fn(&str) -> bool>,
+//!                             |_conflict, _item| ConflictAction::Replace)
+//! }
+//! ```
+use std::ffi::CStr;
+use std::io::Read;
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use std::str;
+
+use ffi;
+
+use error::error_from_sqlite_code;
+use hooks::Action;
+use types::ValueRef;
+use {str_to_cstring, Connection, DatabaseName, Result};
+
+/// An instance of this object is a session that can be used to record changes
+/// to a database.
+///
+/// (See [SQLite doc](https://sqlite.org/session/session.html))
+pub struct Session<'conn> {
+    phantom: PhantomData<&'conn ()>,
+    s: *mut ffi::sqlite3_session,
+    filter: Option<Box<Box<FnMut(&str) -> bool + Send>>>,
+}
+
+impl<'conn> Session<'conn> {
+    /// Create a new session object attached to the main database of `db`.
+    pub fn new(db: &'conn Connection) -> Result<Session<'conn>> {
+        Session::new_with_name(db, DatabaseName::Main)
+    }
+
+    /// Create a new session object attached to the `name` database of `db`.
+    pub fn new_with_name(db: &'conn Connection, name: DatabaseName) -> Result<Session<'conn>> {
+        let name = try!(name.to_cstring());
+
+        let db = db.db.borrow_mut().db;
+
+        let mut s: *mut ffi::sqlite3_session = ptr::null_mut();
+        try!(check(unsafe { ffi::sqlite3session_create(db, name.as_ptr(), &mut s) }));
+
+        Ok(Session {
+               phantom: PhantomData,
+               s: s,
+               filter: None,
+           })
+    }
+
+    /// Set a table filter: when attached to all tables (see `attach`), only
+    /// the changes made to the tables for which `filter` returns `true` are
+    /// recorded.
+    pub fn table_filter<F>(&mut self, filter: Option<F>)
+        where F: FnMut(&str) -> bool + Send + 'static
+    {
+        unsafe extern "C" fn call_boxed_closure(p_arg: *mut c_void,
+                                                tbl_str: *const c_char)
+                                                -> c_int {
+            let boxed_filter: *mut Box<FnMut(&str) -> bool + Send> = p_arg as *mut _;
+            let tbl_name = String::from_utf8_lossy(CStr::from_ptr(tbl_str).to_bytes());
+            let r = catch_unwind(AssertUnwindSafe(|| (*boxed_filter)(&tbl_name)));
+            // A panicking filter ignores the table.
+            r.unwrap_or_default() as c_int
+        }
+
+        match filter {
+            Some(filter) => {
+                let boxed_filter: Box<Box<FnMut(&str) -> bool + Send>> =
+                    Box::new(Box::new(filter));
+                unsafe {
+                    ffi::sqlite3session_table_filter(self.s,
+                                                     Some(call_boxed_closure),
+                                                     &*boxed_filter as *const _ as *mut _);
+                }
+                self.filter = Some(boxed_filter);
+            }
+            _ => {
+                unsafe { ffi::sqlite3session_table_filter(self.s, None, ptr::null_mut()) };
+                self.filter = None;
+            }
+        };
+    }
+
+    /// Attach a table to the session. `None` attaches all the tables of the
+    /// database, including those created later.
+    pub fn attach(&mut self, table: Option<&str>) -> Result<()> {
+        let table = match table {
+            Some(table) => Some(try!(str_to_cstring(table))),
+            None => None,
+        };
+        let table = table.as_ref()
+            .map(|table| table.as_ptr())
+            .unwrap_or(ptr::null());
+        try!(check(unsafe { ffi::sqlite3session_attach(self.s, table) }));
+        Ok(())
+    }
+
+    /// Generate a changeset of the changes recorded so far.
+    pub fn changeset(&mut self) -> Result<Changeset> {
+        let mut n = 0;
+        let mut cs: *mut c_void = ptr::null_mut();
+        try!(check(unsafe { ffi::sqlite3session_changeset(self.s, &mut n, &mut cs) }));
+        Ok(unsafe { Changeset::from_sqlite(cs, n) })
+    }
+
+    /// Generate a patchset of the changes recorded so far.
+    ///
+    /// A patchset is a more compact changeset: it does not contain the
+    /// original values of updated and deleted rows (except for their primary
+    /// key), so it cannot be used to detect every conflict.
+    pub fn patchset(&mut self) -> Result<Changeset> {
+        let mut n = 0;
+        let mut ps: *mut c_void = ptr::null_mut();
+        try!(check(unsafe { ffi::sqlite3session_patchset(self.s, &mut n, &mut ps) }));
+        Ok(unsafe { Changeset::from_sqlite(ps, n) })
+    }
+
+    /// Test if a changeset has recorded any changes
+    pub fn is_empty(&self) -> bool {
+        unsafe { ffi::sqlite3session_isempty(self.s) != 0 }
+    }
+
+    /// Query the current state of the session
+    pub fn is_enabled(&self) -> bool {
+        unsafe { ffi::sqlite3session_enable(self.s, -1) != 0 }
+    }
+
+    /// Enable or disable the recording of changes
+    pub fn set_enabled(&mut self, enabled: bool) {
+        unsafe {
+            ffi::sqlite3session_enable(self.s, if enabled { 1 } else { 0 });
+        }
+    }
+
+    /// Query the current state of the indirect flag
+    pub fn is_indirect(&self) -> bool {
+        unsafe { ffi::sqlite3session_indirect(self.s, -1) != 0 }
+    }
+
+    /// Set or clear the indirect change flag
+    pub fn set_indirect(&mut self, indirect: bool) {
+        unsafe {
+            ffi::sqlite3session_indirect(self.s, if indirect { 1 } else { 0 });
+        }
+    }
+}
+
+impl<'conn> Drop for Session<'conn> {
+    fn drop(&mut self) {
+        // The session must be deleted before its table filter is dropped.
+        unsafe { ffi::sqlite3session_delete(self.s) };
+    }
+}
+
+/// Changeset or patchset, in the binary format produced by SQLite.
+///
+/// (See [SQLite doc](https://sqlite.org/session/changeset_blob.html))
+#[derive(Clone, Debug, PartialEq)]
+pub struct Changeset {
+    bytes: Vec<u8>,
+}
+
+impl Changeset {
+    // Takes ownership of a buffer allocated by SQLite.
+    unsafe fn from_sqlite(cs: *mut c_void, n: c_int) -> Changeset {
+        let bytes = if cs.is_null() {
+            Vec::new()
+        } else {
+            let bytes = slice::from_raw_parts(cs as *const u8, n as usize).to_vec();
+            ffi::sqlite3_free(cs);
+            bytes
+        };
+        Changeset { bytes: bytes }
+    }
+
+    /// Returns the changeset as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the length of the changeset in bytes.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns `true` if the changeset does not contain any change.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Invert a changeset: applying the result undoes the changes of `self`.
+    /// Patchsets cannot be inverted.
+    pub fn invert(&self) -> Result<Changeset> {
+        let mut n = 0;
+        let mut cs: *mut c_void = ptr::null_mut();
+        try!(check(unsafe {
+                       ffi::sqlite3changeset_invert(self.bytes.len() as c_int,
+                                                    self.bytes.as_ptr() as *const c_void,
+                                                    &mut n,
+                                                    &mut cs)
+                   }));
+        Ok(unsafe { Changeset::from_sqlite(cs, n) })
+    }
+
+    /// Concatenate two changesets (or two patchsets) into a single one.
+    pub fn concat(a: &Changeset, b: &Changeset) -> Result<Changeset> {
+        let mut n = 0;
+        let mut cs: *mut c_void = ptr::null_mut();
+        try!(check(unsafe {
+                       ffi::sqlite3changeset_concat(a.bytes.len() as c_int,
+                                                    a.bytes.as_ptr() as *mut c_void,
+                                                    b.bytes.len() as c_int,
+                                                    b.bytes.as_ptr() as *mut c_void,
+                                                    &mut n,
+                                                    &mut cs)
+                   }));
+        Ok(unsafe { Changeset::from_sqlite(cs, n) })
+    }
+
+    /// Create an iterator to traverse the changes of the changeset.
+    pub fn iter(&self) -> Result<ChangesetIter> {
+        let mut it: *mut ffi::sqlite3_changeset_iter = ptr::null_mut();
+        try!(check(unsafe {
+                       ffi::sqlite3changeset_start(&mut it,
+                                                   self.bytes.len() as c_int,
+                                                   self.bytes.as_ptr() as *mut c_void)
+                   }));
+        Ok(ChangesetIter {
+               phantom: PhantomData,
+               it: it,
+           })
+    }
+}
+
+impl From<Vec<u8>> for Changeset {
+    fn from(bytes: Vec<u8>) -> Changeset {
+        Changeset { bytes: bytes }
+    }
+}
+
+impl From<Changeset> for Vec<u8> {
+    fn from(cs: Changeset) -> Vec<u8> {
+        cs.bytes
+    }
+}
+
+/// Cursor for iterating over the elements of a changeset or patchset.
+pub struct ChangesetIter<'changeset> {
+    phantom: PhantomData<&'changeset ()>,
+    it: *mut ffi::sqlite3_changeset_iter,
+}
+
+impl<'changeset> ChangesetIter<'changeset> {
+    /// Attempt to get the next change of the changeset.
+    ///
+    /// This interface is not compatible with Rust's `Iterator` trait, because
+    /// the lifetime of the returned item is tied to the lifetime of `self`
+    /// (see `Rows::next`).
+    pub fn next<'a>(&'a mut self) -> Option<Result<ChangesetItem<'a>>> {
+        unsafe { next_item(self.it) }
+    }
+}
+
+impl<'changeset> Drop for ChangesetIter<'changeset> {
+    fn drop(&mut self) {
+        unsafe { ffi::sqlite3changeset_finalize(self.it) };
+    }
+}
+
+/// Cursor over a changeset (or patchset) read incrementally from an
+/// `std::io::Read`, so that the changeset does not need to be held in memory
+/// as a whole.
+pub struct ChangesetReader<R: Read> {
+    // Read by SQLite through `x_input`; boxed so that its address is stable.
+    _input: Box<R>,
+    it: *mut ffi::sqlite3_changeset_iter,
+}
+
+impl<R: Read> ChangesetReader<R> {
+    /// Start reading a changeset from `input`.
+    pub fn new(input: R) -> Result<ChangesetReader<R>> {
+        let mut input = Box::new(input);
+        let mut it: *mut ffi::sqlite3_changeset_iter = ptr::null_mut();
+        try!(check(unsafe {
+                       ffi::sqlite3changeset_start_strm(&mut it,
+                                                        Some(x_input::<R>),
+                                                        &mut *input as *mut R as *mut c_void)
+                   }));
+        Ok(ChangesetReader {
+               _input: input,
+               it: it,
+           })
+    }
+
+    /// Attempt to get the next change of the changeset.
+    ///
+    /// I/O errors of the underlying reader are reported as `SQLITE_IOERR`
+    /// failures.
+    pub fn next<'a>(&'a mut self) -> Option<Result<ChangesetItem<'a>>> {
+        unsafe { next_item(self.it) }
+    }
+}
+
+impl<R: Read> Drop for ChangesetReader<R> {
+    fn drop(&mut self) {
+        unsafe { ffi::sqlite3changeset_finalize(self.it) };
+    }
+}
+
+unsafe extern "C" fn x_input<R: Read>(p_in: *mut c_void,
+                                      data: *mut c_void,
+                                      len: *mut c_int)
+                                      -> c_int {
+    if p_in.is_null() {
+        return ffi::SQLITE_MISUSE;
+    }
+    let input = p_in as *mut R;
+    let bytes = slice::from_raw_parts_mut(data as *mut u8, *len as usize);
+    match catch_unwind(AssertUnwindSafe(|| (*input).read(bytes))) {
+        Ok(Ok(n)) => {
+            // Zero bytes read signals the end of the input.
+            *len = n as c_int;
+            ffi::SQLITE_OK
+        }
+        _ => ffi::SQLITE_IOERR_READ,
+    }
+}
+
+unsafe fn next_item<'a>(it: *mut ffi::sqlite3_changeset_iter) -> Option<Result<ChangesetItem<'a>>> {
+    match ffi::sqlite3changeset_next(it) {
+        ffi::SQLITE_ROW => {
+            Some(Ok(ChangesetItem {
+                        phantom: PhantomData,
+                        it: it,
+                    }))
+        }
+        ffi::SQLITE_DONE => None,
+        code => Some(Err(error_from_sqlite_code(code, None))),
+    }
+}
+
+/// A change of a changeset: the values it holds remain valid until the next
+/// change is requested.
+pub struct ChangesetItem<'a> {
+    phantom: PhantomData<&'a ()>,
+    it: *mut ffi::sqlite3_changeset_iter,
+}
+
+impl<'a> ChangesetItem<'a> {
+    /// Obtain the table name, the number of columns, the operation and the
+    /// indirect flag of the change.
+    pub fn op(&self) -> Result<Operation<'a>> {
+        let mut table_name: *const c_char = ptr::null();
+        let mut number_of_columns = 0;
+        let mut code = 0;
+        let mut indirect = 0;
+        try!(check(unsafe {
+                       ffi::sqlite3changeset_op(self.it,
+                                                &mut table_name,
+                                                &mut number_of_columns,
+                                                &mut code,
+                                                &mut indirect)
+                   }));
+        let table_name = try!(str::from_utf8(unsafe { CStr::from_ptr(table_name).to_bytes() }));
+        Ok(Operation {
+               table_name: table_name,
+               number_of_columns: number_of_columns,
+               code: Action::from(code),
+               indirect: indirect != 0,
+           })
+    }
+
+    /// Obtain the primary key definition of the table: one flag per column,
+    /// non-zero for the columns that are part of the primary key.
+    pub fn pk(&self) -> Result<&'a [u8]> {
+        let mut p_pk: *mut u8 = ptr::null_mut();
+        let mut n_col = 0;
+        try!(check(unsafe { ffi::sqlite3changeset_pk(self.it, &mut p_pk, &mut n_col) }));
+        Ok(unsafe { slice::from_raw_parts(p_pk, n_col as usize) })
+    }
+
+    /// Obtain the original value of the `col`th column of an `Update` or
+    /// `Delete` change. For an `Update`, columns that were not modified (and
+    /// are not part of the primary key) are `Null`.
+    pub fn old_value(&self, col: usize) -> Result<ValueRef<'a>> {
+        let mut p_value: *mut ffi::sqlite3_value = ptr::null_mut();
+        try!(check(unsafe { ffi::sqlite3changeset_old(self.it, col as c_int, &mut p_value) }));
+        Ok(unsafe { value_ref(p_value) })
+    }
+
+    /// Obtain the new value of the `col`th column of an `Insert` or `Update`
+    /// change. For an `Update`, columns that were not modified are `Null`.
+    pub fn new_value(&self, col: usize) -> Result<ValueRef<'a>> {
+        let mut p_value: *mut ffi::sqlite3_value = ptr::null_mut();
+        try!(check(unsafe { ffi::sqlite3changeset_new(self.it, col as c_int, &mut p_value) }));
+        Ok(unsafe { value_ref(p_value) })
+    }
+
+    /// Obtain the value of the `col`th column of the conflicting row. Only
+    /// available in the conflict handler of `Connection::apply_changeset`,
+    /// for `Data` and `Conflict` conflicts.
+    pub fn conflict(&self, col: usize) -> Result<ValueRef<'a>> {
+        let mut p_value: *mut ffi::sqlite3_value = ptr::null_mut();
+        try!(check(unsafe {
+                       ffi::sqlite3changeset_conflict(self.it, col as c_int, &mut p_value)
+                   }));
+        Ok(unsafe { value_ref(p_value) })
+    }
+
+    /// Obtain the number of foreign key violations. Only available in the
+    /// conflict handler of `Connection::apply_changeset`, for `ForeignKey`
+    /// conflicts.
+    pub fn fk_conflicts(&self) -> Result<i32> {
+        let mut p_out = 0;
+        try!(check(unsafe { ffi::sqlite3changeset_fk_conflicts(self.it, &mut p_out) }));
+        Ok(p_out)
+    }
+}
+
+// SQLite gives a null pointer for values not present in the change.
+unsafe fn value_ref<'a>(p_value: *mut ffi::sqlite3_value) -> ValueRef<'a> {
+    if p_value.is_null() {
+        ValueRef::Null
+    } else {
+        ValueRef::from_value(p_value)
+    }
+}
+
+/// Operation of a `ChangesetItem`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Operation<'item> {
+    table_name: &'item str,
+    number_of_columns: i32,
+    code: Action,
+    indirect: bool,
+}
+
+impl<'item> Operation<'item> {
+    /// Returns the name of the table modified by the change.
+    pub fn table_name(&self) -> &'item str {
+        self.table_name
+    }
+
+    /// Returns the number of columns of the table.
+    pub fn number_of_columns(&self) -> i32 {
+        self.number_of_columns
+    }
+
+    /// Returns the kind of change (`Insert`, `Update` or `Delete`).
+    pub fn code(&self) -> Action {
+        self.code
+    }
+
+    /// Returns `true` if the change was made indirectly (see
+    /// `Session::set_indirect`).
+    pub fn indirect(&self) -> bool {
+        self.indirect
+    }
+}
+
+/// Constants passed to the conflict handler
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(i32)]
+pub enum ConflictType {
+    Unknown = -1,
+    /// The row to update or delete exists but does not hold the expected
+    /// values.
+    Data = ffi::SQLITE_CHANGESET_DATA,
+    /// The row to update or delete does not exist.
+    NotFound = ffi::SQLITE_CHANGESET_NOTFOUND,
+    /// The row to insert already exists.
+    Conflict = ffi::SQLITE_CHANGESET_CONFLICT,
+    /// The change violates a `NOT NULL`, `CHECK` or `UNIQUE` constraint.
+    Constraint = ffi::SQLITE_CHANGESET_CONSTRAINT,
+    /// Foreign key constraints are violated once all the changes are applied.
+    ForeignKey = ffi::SQLITE_CHANGESET_FOREIGN_KEY,
+}
+
+impl From<i32> for ConflictType {
+    fn from(code: i32) -> ConflictType {
+        match code {
+            ffi::SQLITE_CHANGESET_DATA => ConflictType::Data,
+            ffi::SQLITE_CHANGESET_NOTFOUND => ConflictType::NotFound,
+            ffi::SQLITE_CHANGESET_CONFLICT => ConflictType::Conflict,
+            ffi::SQLITE_CHANGESET_CONSTRAINT => ConflictType::Constraint,
+            ffi::SQLITE_CHANGESET_FOREIGN_KEY => ConflictType::ForeignKey,
+            _ => ConflictType::Unknown,
+        }
+    }
+}
+
+/// Constants returned by the conflict handler
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(i32)]
+pub enum ConflictAction {
+    /// Skip the conflicting change.
+    Omit = ffi::SQLITE_CHANGESET_OMIT,
+    /// Replace the conflicting row (only for `Data` and `Conflict`
+    /// conflicts).
+    Replace = ffi::SQLITE_CHANGESET_REPLACE,
+    /// Abort and roll back all the changes applied so far.
+    Abort = ffi::SQLITE_CHANGESET_ABORT,
+}
+
+impl Connection {
+    /// Apply a changeset (or patchset) to the database.
+    ///
+    /// If `filter` is given, only the changes to the tables for which it
+    /// returns `true` are applied. `conflict` decides how to handle each
+    /// conflicting change, given as a `ChangesetItem`; if it returns `ConflictAction::Abort` (or panics),
+    /// all the changes are rolled back and an error is returned.
+    pub fn apply_changeset<F, C>(&self, cs: &Changeset, filter: Option<F>, conflict: C) -> Result<()>
+        where F: FnMut(&str) -> bool,
+              C: FnMut(ConflictType, ChangesetItem) -> ConflictAction
+    {
+        let db = self.db.borrow_mut().db;
+
+        let filtered = filter.is_some();
+        let mut tuple = (filter, conflict);
+        let r = unsafe {
+            ffi::sqlite3changeset_apply(db,
+                                        cs.bytes.len() as c_int,
+                                        cs.bytes.as_ptr() as *mut c_void,
+                                        if filtered {
+                                            Some(call_filter::<F, C>)
+                                        } else {
+                                            None
+                                        },
+                                        Some(call_conflict::<F, C>),
+                                        &mut tuple as *mut (Option<F>, C) as *mut c_void)
+        };
+        self.decode_result(r)
+    }
+}
+
+unsafe extern "C" fn call_filter<F, C>(p_ctx: *mut c_void, tbl_str: *const c_char) -> c_int
+    where F: FnMut(&str) -> bool,
+          C: FnMut(ConflictType, ChangesetItem) -> ConflictAction
+{
+    let tuple: *mut (Option<F>, C) = p_ctx as *mut (Option<F>, C);
+    let tbl_name = String::from_utf8_lossy(CStr::from_ptr(tbl_str).to_bytes());
+    match (*tuple).0 {
+        Some(ref mut filter) => {
+            let r = catch_unwind(AssertUnwindSafe(|| filter(&tbl_name)));
+            // A panicking filter skips the table.
+            r.unwrap_or_default() as c_int
+        }
+        None => 1,
+    }
+}
+
+unsafe extern "C" fn call_conflict<F, C>(p_ctx: *mut c_void,
+                                         e_conflict: c_int,
+                                         p: *mut ffi::sqlite3_changeset_iter)
+                                         -> c_int
+    where F: FnMut(&str) -> bool,
+          C: FnMut(ConflictType, ChangesetItem) -> ConflictAction
+{
+    let tuple: *mut (Option<F>, C) = p_ctx as *mut (Option<F>, C);
+    let conflict_type = ConflictType::from(e_conflict);
+    let item = ChangesetItem {
+        phantom: PhantomData,
+        it: p,
+    };
+    let r = catch_unwind(AssertUnwindSafe(|| ((*tuple).1)(conflict_type, item)));
+    r.unwrap_or(ConflictAction::Abort) as c_int
+}
+
+// The session functions do not record their errors on the database handle.
+fn check(code: c_int) -> Result<()> {
+    if code == ffi::SQLITE_OK {
+        Ok(())
+    } else {
+        Err(error_from_sqlite_code(code, None))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+
+    use super::{Changeset, ChangesetReader, ConflictAction, ConflictType, Session};
+    use hooks::Action;
+    use types::ValueRef;
+    use {Connection, DatabaseName};
+
+    fn one_changeset() -> Changeset {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo(t TEXT PRIMARY KEY NOT NULL);")
+            .unwrap();
+
+        let mut session = Session::new(&db).unwrap();
+        assert!(session.is_empty());
+
+        session.attach(None).unwrap();
+        db.execute("INSERT INTO foo (t) VALUES (?);", &[&"bar"])
+            .unwrap();
+
+        assert!(!session.is_empty());
+        session.changeset().unwrap()
+    }
+
+    #[test]
+    fn test_changeset() {
+        let changeset = one_changeset();
+        assert!(!changeset.is_empty());
+
+        // A changeset survives a round-trip through raw bytes.
+        let bytes: Vec<u8> = changeset.clone().into();
+        assert_eq!(changeset, Changeset::from(bytes));
+    }
+
+    #[test]
+    fn test_patchset() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo(id INTEGER PRIMARY KEY, t TEXT);
+                          INSERT INTO foo VALUES (1, 'one');")
+            .unwrap();
+
+        let mut session = Session::new_with_name(&db, DatabaseName::Main).unwrap();
+        session.attach(Some("foo")).unwrap();
+        db.execute_batch("UPDATE foo SET t = 'uno' WHERE id = 1").unwrap();
+
+        let changeset = session.changeset().unwrap();
+        let patchset = session.patchset().unwrap();
+        assert!(!patchset.is_empty());
+        assert!(patchset.len() < changeset.len());
+    }
+
+    #[test]
+    fn test_apply_changeset() {
+        let changeset = one_changeset();
+
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo(t TEXT PRIMARY KEY NOT NULL);")
+            .unwrap();
+
+        static CALLED: AtomicBool = ATOMIC_BOOL_INIT;
+        db.apply_changeset(&changeset,
+                             None::<fn(&str) -> bool>,
+                             |_conflict, _item| {
+                                 CALLED.store(true, Ordering::Relaxed);
+                                 ConflictAction::Omit
+                             })
+            .unwrap();
+
+        assert!(!CALLED.load(Ordering::Relaxed));
+        let check = db.query_row("SELECT 1 FROM foo WHERE t = ?", &[&"bar"], |row| {
+                row.get::<_, i32>(0)
+            })
+            .unwrap();
+        assert_eq!(1, check);
+
+        // conflict expected when same changeset applied again on the same db
+        let mut conflicts = Vec::new();
+        db.apply_changeset(&changeset,
+                             None::<fn(&str) -> bool>,
+                             |conflict, item| {
+                                 assert_eq!("bar", item.conflict(0).unwrap().as_str().unwrap());
+                                 conflicts.push(conflict);
+                                 ConflictAction::Omit
+                             })
+            .unwrap();
+        assert_eq!(conflicts, vec![ConflictType::Conflict]);
+
+        assert!(db.apply_changeset(&changeset,
+                                     None::<fn(&str) -> bool>,
+                                     |_conflict, _item| ConflictAction::Abort)
+                    .is_err());
+    }
+
+    #[test]
+    fn test_apply_changeset_filter() {
+        let changeset = one_changeset();
+
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo(t TEXT PRIMARY KEY NOT NULL);")
+            .unwrap();
+
+        let mut tables = Vec::new();
+        db.apply_changeset(&changeset,
+                             Some(|table: &str| {
+                                      tables.push(table.to_owned());
+                                      false
+                                  }),
+                             |_conflict, _item| ConflictAction::Abort)
+            .unwrap();
+        assert_eq!(tables, vec!["foo"]);
+
+        let count: i64 = db.query_row("SELECT COUNT(*) FROM foo", &[], |row| row.get(0))
+            .unwrap();
+        assert_eq!(0, count);
+    }
+
+    #[test]
+    fn test_table_filter() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo(t TEXT PRIMARY KEY NOT NULL);
+                          CREATE TABLE bar(t TEXT PRIMARY KEY NOT NULL);")
+            .unwrap();
+
+        let mut session = Session::new(&db).unwrap();
+        session.table_filter(Some(|table: &str| table == "foo"));
+        session.attach(None).unwrap();
+
+        db.execute_batch("INSERT INTO bar VALUES ('ignored');").unwrap();
+        assert!(session.is_empty());
+
+        db.execute_batch("INSERT INTO foo VALUES ('recorded');").unwrap();
+        assert!(!session.is_empty());
+
+        session.table_filter(None::<fn(&str) -> bool>);
+        assert!(!session.changeset().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_session_empty() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo(t TEXT PRIMARY KEY NOT NULL);")
+            .unwrap();
+
+        let mut session = Session::new(&db).unwrap();
+        assert!(session.is_empty());
+
+        session.attach(None).unwrap();
+        assert!(session.is_empty());
+        assert!(session.changeset().unwrap().is_empty());
+
+        assert!(session.is_enabled());
+        session.set_enabled(false);
+        assert!(!session.is_enabled());
+        db.execute_batch("INSERT INTO foo VALUES ('untracked');").unwrap();
+        assert!(session.is_empty());
+
+        assert!(!session.is_indirect());
+        session.set_indirect(true);
+        assert!(session.is_indirect());
+    }
+
+    #[test]
+    fn test_changeset_iter() {
+        let changeset = one_changeset();
+        let mut iter = changeset.iter().unwrap();
+        {
+            let item = iter.next().unwrap().unwrap();
+            let op = item.op().unwrap();
+            assert_eq!("foo", op.table_name());
+            assert_eq!(1, op.number_of_columns());
+            assert_eq!(Action::Insert, op.code());
+            assert!(!op.indirect());
+
+            assert_eq!(&[1], item.pk().unwrap());
+            assert_eq!(ValueRef::Text("bar"), item.new_value(0).unwrap());
+            assert!(item.old_value(0).is_err());
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_changeset_update() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo(id INTEGER PRIMARY KEY, a TEXT, b INTEGER);
+                          INSERT INTO foo VALUES (1, 'x', 10);")
+            .unwrap();
+
+        let mut session = Session::new(&db).unwrap();
+        session.attach(None).unwrap();
+        db.execute_batch("UPDATE foo SET b = 11 WHERE id = 1").unwrap();
+        let changeset = session.changeset().unwrap();
+
+        let mut iter = changeset.iter().unwrap();
+        let item = iter.next().unwrap().unwrap();
+        assert_eq!(Action::Update, item.op().unwrap().code());
+        assert_eq!(&[1, 0, 0], item.pk().unwrap());
+        assert_eq!(ValueRef::Integer(1), item.old_value(0).unwrap());
+        // Unmodified columns are not part of the change.
+        assert_eq!(ValueRef::Null, item.old_value(1).unwrap());
+        assert_eq!(ValueRef::Integer(10), item.old_value(2).unwrap());
+        assert_eq!(ValueRef::Integer(11), item.new_value(2).unwrap());
+    }
+
+    #[test]
+    fn test_invert_concat() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE foo(t TEXT PRIMARY KEY NOT NULL);")
+            .unwrap();
+
+        let mut session = Session::new(&db).unwrap();
+        session.attach(None).unwrap();
+        db.execute_batch("INSERT INTO foo VALUES ('a');").unwrap();
+        let first = session.changeset().unwrap();
+        drop(session);
+
+        let mut session = Session::new(&db).unwrap();
+        session.attach(None).unwrap();
+        db.execute_batch("INSERT INTO foo VALUES ('b');").unwrap();
+        let second = session.changeset().unwrap();
+        drop(session);
+
+        let both = Changeset::concat(&first, &second).unwrap();
+        let mut count = 0;
+        {
+            let mut iter = both.iter().unwrap();
+            while let Some(item) = iter.next() {
+                assert_eq!(Action::Insert, item.unwrap().op().unwrap().code());
+                count += 1;
+            }
+        }
+        assert_eq!(2, count);
+
+        // Applying the inverse undoes the inserts.
+        let undo = both.invert().unwrap();
+        {
+            let mut iter = undo.iter().unwrap();
+            let item = iter.next().unwrap().unwrap();
+            assert_eq!(Action::Delete, item.op().unwrap().code());
+        }
+        db.apply_changeset(&undo,
+                             None::<fn(&str) -> bool>,
+                             |_conflict, _item| ConflictAction::Abort)
+            .unwrap();
+        let count: i64 = db.query_row("SELECT COUNT(*) FROM foo", &[], |row| row.get(0))
+            .unwrap();
+        assert_eq!(0, count);
+    }
+
+    #[test]
+    fn test_changeset_reader() {
+        let changeset = one_changeset();
+        let bytes = changeset.as_bytes();
+
+        let mut reader = ChangesetReader::new(bytes).unwrap();
+        {
+            let item = reader.next().unwrap().unwrap();
+            assert_eq!("foo", item.op().unwrap().table_name());
+            assert_eq!(ValueRef::Text("bar"), item.new_value(0).unwrap());
+        }
+        assert!(reader.next().is_none());
+
+        // A truncated changeset is reported as an error.
+        let mut reader = ChangesetReader::new(&bytes[..bytes.len() - 1]).unwrap();
+        assert!(reader.next().unwrap().is_err());
+    }
+}
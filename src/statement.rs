@@ -1,12 +1,17 @@
 use std::{convert, fmt, mem, ptr, result, str};
 use std::ffi::CStr;
 use std::os::raw::{c_char, c_int, c_void};
+#[cfg(feature = "array")]
+use std::rc::Rc;
 use std::slice::from_raw_parts;
 
 use super::ffi;
-use super::{Connection, RawStatement, Result, Error, ValueRef, Row, Rows, AndThenRows, MappedRows};
+use super::{Column, Connection, RawStatement, Result, Error, ValueRef, Row, Rows, AndThenRows, MappedRows,
+            Params};
 use super::str_to_cstring;
 use types::{ToSql, ToSqlOutput};
+#[cfg(feature = "array")]
+use vtab::array::{free_array, ARRAY_TYPE};
 use row::{RowsCrateImpl, MappedRowsCrateImpl, AndThenRowsCrateImpl};
 
 /// A prepared statement.
@@ -28,6 +33,16 @@ impl<'conn> Statement<'conn> {
         cols
     }
 
+    /// Get information about all the columns in the result set of the prepared statement.
+    pub fn columns(&self) -> Vec<Column> {
+        let n = self.column_count();
+        let mut cols = Vec::with_capacity(n as usize);
+        for i in 0..n {
+            cols.push(Column::new(&self.stmt, i));
+        }
+        cols
+    }
+
     /// Return the number of columns in the result set returned by the prepared statement.
     pub fn column_count(&self) -> i32 {
         self.stmt.column_count()
@@ -52,7 +67,8 @@ impl<'conn> Statement<'conn> {
         Err(Error::InvalidColumnName(String::from(name)))
     }
 
-    /// Execute the prepared statement.
+    /// Execute the prepared statement with the parameters `params` (see
+    /// `Params` for the accepted types).
     ///
     /// On success, returns the number of rows that were changed or inserted or deleted (via
     /// `sqlite3_changes`).
@@ -69,42 +85,30 @@ impl<'conn> Statement<'conn> {
     ///
     ///     Ok(())
     /// }
+    ///
+    /// fn insert(conn: &Connection) -> Result<i32> {
+    ///     let mut stmt = try!(conn.prepare("INSERT INTO test (name) VALUES (:name)"));
+    ///     stmt.execute(&[(":name", &"one")])
+    /// }
     /// ```
     ///
     /// # Failure
     ///
     /// Will return `Err` if binding parameters fails, the executed statement returns rows (in
     /// which case `query` should be used instead), or the underling SQLite call fails.
-    pub fn execute(&mut self, params: &[&ToSql]) -> Result<c_int> {
-        try!(self.bind_parameters(params));
+    pub fn execute<P: Params>(&mut self, params: P) -> Result<c_int> {
+        try!(params.bind_in(self));
         self.execute_with_bound_parameters()
     }
 
-    /// Execute the prepared statement with named parameter(s). If any parameters
-    /// that were in the prepared statement are not included in `params`, they
-    /// will continue to use the most-recently bound value from a previous call
-    /// to `execute_named`, or `NULL` if they have never been bound.
+    /// Execute the prepared statement with named parameter(s).
     ///
-    /// On success, returns the number of rows that were changed or inserted or deleted (via
-    /// `sqlite3_changes`).
+    /// ## Deprecated
     ///
-    /// ## Example
-    ///
-    /// ```rust,no_run
-    /// # use rusqlite::{Connection, Result};
-    /// fn insert(conn: &Connection) -> Result<i32> {
-    ///     let mut stmt = try!(conn.prepare("INSERT INTO test (name) VALUES (:name)"));
-    ///     stmt.execute_named(&[(":name", &"one")])
-    /// }
-    /// ```
-    ///
-    /// # Failure
-    ///
-    /// Will return `Err` if binding parameters fails, the executed statement returns rows (in
-    /// which case `query` should be used instead), or the underling SQLite call fails.
+    /// `execute` now accepts named parameters.
+    #[deprecated(since = "0.14.0", note = "Use execute instead")]
     pub fn execute_named(&mut self, params: &[(&str, &ToSql)]) -> Result<c_int> {
-        try!(self.bind_parameters_named(params));
-        self.execute_with_bound_parameters()
+        self.execute(params)
     }
 
     /// Execute an INSERT and return the ROWID.
@@ -119,7 +123,7 @@ impl<'conn> Statement<'conn> {
     /// # Failure
     ///
     /// Will return `Err` if no row is inserted or many rows are inserted.
-    pub fn insert(&mut self, params: &[&ToSql]) -> Result<i64> {
+    pub fn insert<P: Params>(&mut self, params: P) -> Result<i64> {
         let changes = try!(self.execute(params));
         match changes {
             1 => Ok(self.conn.last_insert_rowid()),
@@ -149,28 +153,10 @@ impl<'conn> Statement<'conn> {
     ///
     ///     Ok(names)
     /// }
-    /// ```
-    ///
-    /// ## Failure
-    ///
-    /// Will return `Err` if binding parameters fails.
-    pub fn query<'a>(&'a mut self, params: &[&ToSql]) -> Result<Rows<'a>> {
-        try!(self.bind_parameters(params));
-        Ok(Rows::new(self))
-    }
-
-    /// Execute the prepared statement with named parameter(s), returning a handle for the
-    /// resulting rows. If any parameters that were in the prepared statement are not included in
-    /// `params`, they will continue to use the most-recently bound value from a previous call to
-    /// `query_named`, or `NULL` if they have never been bound.
-    ///
-    /// ## Example
     ///
-    /// ```rust,no_run
-    /// # use rusqlite::{Connection, Result};
     /// fn query(conn: &Connection) -> Result<()> {
     ///     let mut stmt = try!(conn.prepare("SELECT * FROM test where name = :name"));
-    ///     let mut rows = try!(stmt.query_named(&[(":name", &"one")]));
+    ///     let mut rows = try!(stmt.query(&[(":name", &"one")]));
     ///     while let Some(row) = rows.next() {
     ///         // ...
     ///     }
@@ -178,14 +164,25 @@ impl<'conn> Statement<'conn> {
     /// }
     /// ```
     ///
-    /// # Failure
+    /// ## Failure
     ///
     /// Will return `Err` if binding parameters fails.
-    pub fn query_named<'a>(&'a mut self, params: &[(&str, &ToSql)]) -> Result<Rows<'a>> {
-        try!(self.bind_parameters_named(params));
+    pub fn query<'a, P: Params>(&'a mut self, params: P) -> Result<Rows<'a>> {
+        try!(params.bind_in(self));
         Ok(Rows::new(self))
     }
 
+    /// Execute the prepared statement with named parameter(s), returning a handle for the
+    /// resulting rows.
+    ///
+    /// ## Deprecated
+    ///
+    /// `query` now accepts named parameters.
+    #[deprecated(since = "0.14.0", note = "Use query instead")]
+    pub fn query_named<'a>(&'a mut self, params: &[(&str, &ToSql)]) -> Result<Rows<'a>> {
+        self.query(params)
+    }
+
     /// Executes the prepared statement and maps a function over the resulting rows, returning
     /// an iterator over the mapped function results.
     ///
@@ -209,73 +206,34 @@ impl<'conn> Statement<'conn> {
     /// ## Failure
     ///
     /// Will return `Err` if binding parameters fails.
-    pub fn query_map<'a, T, F>(&'a mut self, params: &[&ToSql], f: F) -> Result<MappedRows<'a, F>>
-        where F: FnMut(&Row) -> T
+    pub fn query_map<'a, T, P, F>(&'a mut self, params: P, f: F) -> Result<MappedRows<'a, F>>
+        where P: Params,
+              F: FnMut(&Row) -> T
     {
         let rows = self.query(params)?;
         Ok(MappedRows::new(rows, f))
     }
 
     /// Execute the prepared statement with named parameter(s), returning an iterator over the
-    /// result of calling the mapping function over the query's rows. If any parameters that were
-    /// in the prepared statement are not included in `params`, they will continue to use the
-    /// most-recently bound value from a previous call to `query_named`, or `NULL` if they have
-    /// never been bound.
+    /// result of calling the mapping function over the query's rows.
     ///
-    /// ## Example
+    /// ## Deprecated
     ///
-    /// ```rust,no_run
-    /// # use rusqlite::{Connection, Result};
-    /// fn get_names(conn: &Connection) -> Result<Vec<String>> {
-    ///     let mut stmt = try!(conn.prepare("SELECT name FROM people WHERE id = :id"));
-    ///     let rows = try!(stmt.query_map_named(&[(":id", &"one")], |row| row.get(0)));
-    ///
-    ///     let mut names = Vec::new();
-    ///     for name_result in rows {
-    ///         names.push(try!(name_result));
-    ///     }
-    ///
-    ///     Ok(names)
-    /// }
-    /// ```
-    ///
-    /// ## Failure
-    ///
-    /// Will return `Err` if binding parameters fails.
+    /// `query_map` now accepts named parameters.
+    #[deprecated(since = "0.14.0", note = "Use query_map instead")]
     pub fn query_map_named<'a, T, F>(&'a mut self,
                                      params: &[(&str, &ToSql)],
                                      f: F)
                                      -> Result<MappedRows<'a, F>>
         where F: FnMut(&Row) -> T
     {
-        let rows = self.query_named(params)?;
-        Ok(MappedRows::new(rows, f))
+        self.query_map(params, f)
     }
 
     /// Executes the prepared statement and maps a function over the resulting
     /// rows, where the function returns a `Result` with `Error` type implementing
     /// `std::convert::From<Error>` (so errors can be unified).
     ///
-    /// # Failure
-    ///
-    /// Will return `Err` if binding parameters fails.
-    pub fn query_and_then<'a, T, E, F>(&'a mut self,
-                                       params: &[&ToSql],
-                                       f: F)
-                                       -> Result<AndThenRows<'a, F>>
-        where E: convert::From<Error>,
-              F: FnMut(&Row) -> result::Result<T, E>
-    {
-        let rows = self.query(params)?;
-        Ok(AndThenRows::new(rows, f))
-    }
-
-    /// Execute the prepared statement with named parameter(s), returning an iterator over the
-    /// result of calling the mapping function over the query's rows. If any parameters that were
-    /// in the prepared statement are not included in `params`, they will continue to use the
-    /// most-recently bound value from a previous call to `query_named`, or `NULL` if they have
-    /// never been bound.
-    ///
     /// ## Example
     ///
     /// ```rust,no_run
@@ -289,7 +247,7 @@ impl<'conn> Statement<'conn> {
     ///
     /// fn get_names(conn: &Connection) -> Result<Vec<Person>> {
     ///     let mut stmt = try!(conn.prepare("SELECT name FROM people WHERE id = :id"));
-    ///     let rows = try!(stmt.query_and_then_named(&[(":id", &"one")], |row| {
+    ///     let rows = try!(stmt.query_and_then(&[(":id", &"one")], |row| {
     ///         name_to_person(row.get(0))
     ///     }));
     ///
@@ -302,9 +260,28 @@ impl<'conn> Statement<'conn> {
     /// }
     /// ```
     ///
-    /// ## Failure
+    /// # Failure
     ///
     /// Will return `Err` if binding parameters fails.
+    pub fn query_and_then<'a, T, E, P, F>(&'a mut self,
+                                          params: P,
+                                          f: F)
+                                          -> Result<AndThenRows<'a, F>>
+        where P: Params,
+              E: convert::From<Error>,
+              F: FnMut(&Row) -> result::Result<T, E>
+    {
+        let rows = self.query(params)?;
+        Ok(AndThenRows::new(rows, f))
+    }
+
+    /// Execute the prepared statement with named parameter(s), returning an iterator over the
+    /// result of calling the fallible mapping function over the query's rows.
+    ///
+    /// ## Deprecated
+    ///
+    /// `query_and_then` now accepts named parameters.
+    #[deprecated(since = "0.14.0", note = "Use query_and_then instead")]
     pub fn query_and_then_named<'a, T, E, F>(&'a mut self,
                                              params: &[(&str, &ToSql)],
                                              f: F)
@@ -312,13 +289,12 @@ impl<'conn> Statement<'conn> {
         where E: convert::From<Error>,
               F: FnMut(&Row) -> result::Result<T, E>
     {
-        let rows = self.query_named(params)?;
-        Ok(AndThenRows::new(rows, f))
+        self.query_and_then(params, f)
     }
 
     /// Return `true` if a query in the SQL statement it executes returns one or more rows
     /// and `false` if the SQL returns an empty set.
-    pub fn exists(&mut self, params: &[&ToSql]) -> Result<bool> {
+    pub fn exists<P: Params>(&mut self, params: P) -> Result<bool> {
         let mut rows = try!(self.query(params));
         let exists = {
             match rows.next() {
@@ -336,8 +312,9 @@ impl<'conn> Statement<'conn> {
     /// # Failure
     ///
     /// Will return `Err` if the underlying SQLite call fails.
-    pub fn query_row<T, F>(&mut self, params: &[&ToSql], f: F) -> Result<T>
-        where F: FnOnce(&Row) -> T
+    pub fn query_row<T, P, F>(&mut self, params: P, f: F) -> Result<T>
+        where P: Params,
+              F: FnOnce(&Row) -> T
     {
         let mut rows = try!(self.query(params));
 
@@ -367,23 +344,47 @@ impl<'conn> Statement<'conn> {
         Ok(self.stmt.bind_parameter_index(&c_name))
     }
 
-    fn bind_parameters(&mut self, params: &[&ToSql]) -> Result<()> {
-        assert_eq!(params.len() as c_int, self.stmt.bind_parameter_count(),
-                "incorrect number of parameters to query(): expected {}, got {}",
-                self.stmt.bind_parameter_count(),
-                params.len());
+    /// Return the number of SQL parameters of the prepared statement, i.e. the
+    /// largest parameter index.
+    pub fn parameter_count(&self) -> i32 {
+        self.stmt.bind_parameter_count()
+    }
+
+    /// Return the name of the SQL parameter at `index` (starting at 1),
+    /// including its prefix (e.g. ":name"), or `None` for a nameless
+    /// parameter (`?`) or an invalid index.
+    pub fn parameter_name(&self, index: i32) -> Option<&str> {
+        self.stmt
+            .bind_parameter_name(index)
+            .map(|name| str::from_utf8(name.to_bytes()).expect("invalid UTF-8 parameter name"))
+    }
 
-        for (i, p) in params.iter().enumerate() {
-            try!(self.bind_parameter(*p, (i + 1) as c_int));
+    pub(crate) fn bind_parameters<P>(&mut self, params: P) -> Result<()>
+        where P: IntoIterator,
+              P::Item: ToSql
+    {
+        let expected = self.stmt.bind_parameter_count();
+        let mut index = 0;
+        for p in params {
+            index += 1; // The leftmost SQL parameter has an index of 1.
+            if index <= expected {
+                try!(self.bind_parameter(&p, index));
+            }
         }
+        assert_eq!(index, expected,
+                "incorrect number of parameters to query(): expected {}, got {}",
+                expected,
+                index);
 
         Ok(())
     }
 
-    fn bind_parameters_named(&mut self, params: &[(&str, &ToSql)]) -> Result<()> {
+    pub(crate) fn bind_parameters_named<T>(&mut self, params: &[(&str, &T)]) -> Result<()>
+        where T: ToSql + ?Sized
+    {
         for &(name, value) in params {
             if let Some(i) = try!(self.parameter_index(name)) {
-                try!(self.bind_parameter(value, i));
+                try!(self.bind_parameter(&value, i));
             } else {
                 return Err(Error::InvalidParameterName(name.into()));
             }
@@ -391,7 +392,7 @@ impl<'conn> Statement<'conn> {
         Ok(())
     }
 
-    fn bind_parameter(&self, param: &ToSql, col: c_int) -> Result<()> {
+    pub(crate) fn bind_parameter(&self, param: &ToSql, col: c_int) -> Result<()> {
         let value = try!(param.to_sql());
 
         let ptr = unsafe { self.stmt.ptr() };
@@ -404,6 +405,17 @@ impl<'conn> Statement<'conn> {
                 return self.conn
                            .decode_result(unsafe { ffi::sqlite3_bind_zeroblob(ptr, col, len) });
             }
+            #[cfg(feature = "array")]
+            ToSqlOutput::Array(a) => {
+                return self.conn
+                           .decode_result(unsafe {
+                                              ffi::sqlite3_bind_pointer(ptr,
+                                                                        col,
+                                                                        Rc::into_raw(a) as *mut c_void,
+                                                                        ARRAY_TYPE,
+                                                                        Some(free_array))
+                                          });
+            }
         };
         self.conn
             .decode_result(match value {
@@ -572,6 +584,7 @@ mod test {
     use {Connection, Error, Result};
 
     #[test]
+    #[allow(deprecated)]
     fn test_execute_named() {
         let db = Connection::open_in_memory().unwrap();
         db.execute_batch("CREATE TABLE foo(x INTEGER)").unwrap();
@@ -591,6 +604,7 @@ mod test {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_stmt_execute_named() {
         let db = Connection::open_in_memory().unwrap();
         let sql = "CREATE TABLE test (id INTEGER PRIMARY KEY NOT NULL, name TEXT NOT NULL, flag \
@@ -609,6 +623,7 @@ mod test {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_query_named() {
         let db = Connection::open_in_memory().unwrap();
         let sql = r#"
@@ -626,6 +641,7 @@ mod test {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_query_map_named() {
         let db = Connection::open_in_memory().unwrap();
         let sql = r#"
@@ -647,6 +663,7 @@ mod test {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_query_and_then_named() {
 
         let db = Connection::open_in_memory().unwrap();
@@ -682,6 +699,7 @@ mod test {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_unbound_parameters_are_null() {
         let db = Connection::open_in_memory().unwrap();
         let sql = "CREATE TABLE test (x TEXT, y TEXT)";
@@ -698,6 +716,7 @@ mod test {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_unbound_parameters_are_reused() {
         let db = Connection::open_in_memory().unwrap();
         let sql = "CREATE TABLE test (x TEXT, y TEXT)";
@@ -714,6 +733,18 @@ mod test {
         assert_eq!(result, "one");
     }
 
+    #[test]
+    fn test_parameter_name() {
+        let db = Connection::open_in_memory().unwrap();
+        let stmt = db.prepare("SELECT :x, ?, @y, :x, $z").unwrap();
+        assert_eq!(4, stmt.parameter_count());
+        assert_eq!(Some(":x"), stmt.parameter_name(1));
+        assert_eq!(None, stmt.parameter_name(2));
+        assert_eq!(Some("@y"), stmt.parameter_name(3));
+        assert_eq!(Some("$z"), stmt.parameter_name(4));
+        assert_eq!(None, stmt.parameter_name(5));
+    }
+
     #[test]
     fn test_insert() {
         let db = Connection::open_in_memory().unwrap();
@@ -1,10 +1,19 @@
 //! Tracing and profiling functions. Error and warning log.
 
+#[cfg(any(feature = "bundled", feature = "modern_sqlite"))]
+use std::os::raw::c_uint;
 use std::os::raw::{c_char, c_int, c_void};
 use std::ffi::{CStr, CString};
 use std::mem;
 use std::ptr;
 use std::time::Duration;
+#[cfg(any(feature = "bundled", feature = "modern_sqlite"))]
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+#[cfg(feature = "log")]
+extern crate log as log_crate;
+#[cfg(feature = "tracing")]
+extern crate tracing as tracing_crate;
 
 use super::ffi;
 use {Result, Connection};
@@ -113,6 +122,207 @@ impl Connection {
     }
 }
 
+#[cfg(any(feature = "bundled", feature = "modern_sqlite"))]
+bitflags! {
+    /// Events to be traced by `Connection::trace_v2`.
+    #[repr(C)]
+    pub struct TraceEventCodes: ::std::os::raw::c_uint {
+        /// A prepared statement starts running (`TraceEvent::Stmt`).
+        const STMT = ffi::SQLITE_TRACE_STMT as ::std::os::raw::c_uint;
+        /// A prepared statement finishes (`TraceEvent::Profile`).
+        const PROFILE = ffi::SQLITE_TRACE_PROFILE as ::std::os::raw::c_uint;
+        /// A prepared statement produces a row (`TraceEvent::Row`).
+        const ROW = ffi::SQLITE_TRACE_ROW as ::std::os::raw::c_uint;
+        /// The database connection closes (`TraceEvent::Close`).
+        const CLOSE = ffi::SQLITE_TRACE_CLOSE as ::std::os::raw::c_uint;
+    }
+}
+
+/// An event reported to a `Connection::trace_v2` callback.
+#[cfg(any(feature = "bundled", feature = "modern_sqlite"))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceEvent<'s> {
+    /// A prepared statement starts running, with its SQL text and bound
+    /// parameters expanded, or the comment (starting with `--`) introducing a
+    /// trigger.
+    Stmt(&'s str),
+    /// A prepared statement finished, with its SQL text and its approximate
+    /// running time.
+    Profile(&'s str, Duration),
+    /// A prepared statement produced a row.
+    Row,
+    /// The database connection is closing.
+    Close,
+}
+
+#[cfg(any(feature = "bundled", feature = "modern_sqlite"))]
+impl Connection {
+    /// Register or clear a callback function for the events in `mask`.
+    ///
+    /// Unlike `trace` and `profile`, the callback may capture state. There can
+    /// only be a single `trace_v2` callback for each database connection, and
+    /// setting one clears any `trace` or `profile` callback.
+    ///
+    /// Note: This requires SQLite 3.14.0 or later.
+    pub fn trace_v2<F>(&self, mask: TraceEventCodes, trace_fn: Option<F>)
+        where F: FnMut(TraceEvent) + Send + 'static
+    {
+        unsafe extern "C" fn trace_callback(evt: c_uint,
+                                            ctx: *mut c_void,
+                                            p: *mut c_void,
+                                            x: *mut c_void)
+                                            -> c_int {
+            let boxed_fn = ctx as *mut Box<FnMut(TraceEvent) + Send>;
+            let _ = catch_unwind(AssertUnwindSafe(|| match evt as i32 {
+                ffi::SQLITE_TRACE_STMT => {
+                    let z_sql = x as *const c_char;
+                    if CStr::from_ptr(z_sql).to_bytes().starts_with(b"--") {
+                        let s = String::from_utf8_lossy(CStr::from_ptr(z_sql).to_bytes());
+                        (*boxed_fn)(TraceEvent::Stmt(&s));
+                    } else {
+                        let expanded = ffi::sqlite3_expanded_sql(p as *mut ffi::sqlite3_stmt);
+                        let c_slice = if expanded.is_null() {
+                            CStr::from_ptr(z_sql).to_bytes()
+                        } else {
+                            CStr::from_ptr(expanded).to_bytes()
+                        };
+                        {
+                            let s = String::from_utf8_lossy(c_slice);
+                            (*boxed_fn)(TraceEvent::Stmt(&s));
+                        }
+                        ffi::sqlite3_free(expanded as *mut c_void);
+                    }
+                }
+                ffi::SQLITE_TRACE_PROFILE => {
+                    let z_sql = ffi::sqlite3_sql(p as *mut ffi::sqlite3_stmt);
+                    let s = String::from_utf8_lossy(CStr::from_ptr(z_sql).to_bytes());
+                    let nanoseconds = *(x as *const i64) as u64;
+                    const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+                    let duration = Duration::new(nanoseconds / NANOS_PER_SEC,
+                                                 (nanoseconds % NANOS_PER_SEC) as u32);
+                    (*boxed_fn)(TraceEvent::Profile(&s, duration));
+                }
+                ffi::SQLITE_TRACE_ROW => (*boxed_fn)(TraceEvent::Row),
+                ffi::SQLITE_TRACE_CLOSE => (*boxed_fn)(TraceEvent::Close),
+                _ => {}
+            }));
+            0
+        }
+
+        let mut c = self.db.borrow_mut();
+        let boxed_fn = trace_fn.map(|f| {
+            let trace_fn: Box<FnMut(TraceEvent) + Send> = Box::new(f);
+            Box::new(trace_fn)
+        });
+        match boxed_fn {
+            Some(ref trace_fn) => unsafe {
+                let p_arg = &**trace_fn as *const Box<FnMut(TraceEvent) + Send> as *mut c_void;
+                ffi::sqlite3_trace_v2(c.db(), mask.bits(), Some(trace_callback), p_arg);
+            },
+            None => unsafe {
+                ffi::sqlite3_trace_v2(c.db(), 0, None, ptr::null_mut());
+            },
+        }
+        c.trace_v2 = boxed_fn;
+    }
+}
+
+/// Send the SQLite error log to the `log` crate, under the `rusqlite::trace`
+/// target. Messages are logged at the `Info` level for `SQLITE_OK` and
+/// `SQLITE_NOTICE`, `Warn` for `SQLITE_WARNING` and `SQLITE_SCHEMA`, and
+/// `Error` for any other primary result code.
+///
+/// This function is unsafe for the same reasons as `config_log`.
+#[cfg(feature = "log")]
+pub unsafe fn config_log_to_log_crate() -> Result<()> {
+    fn log_callback(err: c_int, msg: &str) {
+        let level = match err & 0xff {
+            ffi::SQLITE_OK | ffi::SQLITE_NOTICE => log_crate::Level::Info,
+            ffi::SQLITE_WARNING | ffi::SQLITE_SCHEMA => log_crate::Level::Warn,
+            _ => log_crate::Level::Error,
+        };
+        log_crate::log!(level, "({}) {}", err, msg);
+    }
+
+    config_log(Some(log_callback))
+}
+
+/// Send the SQLite error log to the `tracing` crate, as events with a `code`
+/// field. Levels are chosen as in `config_log_to_log_crate`.
+///
+/// This function is unsafe for the same reasons as `config_log`.
+#[cfg(feature = "tracing")]
+pub unsafe fn config_log_to_tracing() -> Result<()> {
+    fn log_callback(err: c_int, msg: &str) {
+        match err & 0xff {
+            ffi::SQLITE_OK | ffi::SQLITE_NOTICE => tracing_crate::info!(code = err, "{}", msg),
+            ffi::SQLITE_WARNING | ffi::SQLITE_SCHEMA => tracing_crate::warn!(code = err, "{}", msg),
+            _ => tracing_crate::error!(code = err, "{}", msg),
+        }
+    }
+
+    config_log(Some(log_callback))
+}
+
+#[cfg(any(feature = "log", feature = "tracing"))]
+impl Connection {
+    /// Log the statements run on this connection with the `log` crate: each
+    /// statement with its bound parameters at the `Trace` level when it
+    /// starts, and with its elapsed time at the `Debug` level when it
+    /// finishes. Messages are prefixed with the database path.
+    ///
+    /// This replaces any `trace_v2` callback.
+    #[cfg(feature = "log")]
+    pub fn trace_to_log_crate(&self) {
+        let path = self.path_for_trace();
+        self.trace_v2(TraceEventCodes::STMT | TraceEventCodes::PROFILE,
+                      Some(move |evt: TraceEvent| match evt {
+                               TraceEvent::Stmt(sql) => log_crate::trace!("{}: {}", path, sql),
+                               TraceEvent::Profile(sql, elapsed) => {
+                                   log_crate::debug!("{}: {} ({:?})", path, sql, elapsed)
+                               }
+                               _ => {}
+                           }));
+    }
+
+    /// Report the statements run on this connection to the `tracing` crate:
+    /// a `TRACE` event with its bound parameters when a statement starts,
+    /// and a `DEBUG` event with its elapsed time when it finishes. Events
+    /// carry `path` and `sql` fields, and finished statements an
+    /// `elapsed_us` field.
+    ///
+    /// This replaces any `trace_v2` callback.
+    #[cfg(feature = "tracing")]
+    pub fn trace_to_tracing(&self) {
+        let path = self.path_for_trace();
+        self.trace_v2(TraceEventCodes::STMT | TraceEventCodes::PROFILE,
+                      Some(move |evt: TraceEvent| match evt {
+                               TraceEvent::Stmt(sql) => {
+                                   tracing_crate::trace!(path = path.as_str(),
+                                                         sql = sql,
+                                                         "statement started")
+                               }
+                               TraceEvent::Profile(sql, elapsed) => {
+                                   let elapsed_us = elapsed.as_secs() * 1_000_000 +
+                                                    u64::from(elapsed.subsec_nanos() / 1_000);
+                                   tracing_crate::debug!(path = path.as_str(),
+                                                         sql = sql,
+                                                         elapsed_us = elapsed_us,
+                                                         "statement finished")
+                               }
+                               _ => {}
+                           }));
+    }
+
+    fn path_for_trace(&self) -> String {
+        match self.path {
+            Some(ref path) => path.display().to_string(),
+            None => ":memory:".to_owned(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::sync::Mutex;
@@ -168,4 +378,138 @@ mod test {
         assert_eq!(profiled.len(), 1);
         assert_eq!(profiled[0].0, "PRAGMA application_id = 1");
     }
+
+    #[test]
+    #[cfg(any(feature = "bundled", feature = "modern_sqlite"))]
+    fn test_trace_v2() {
+        use std::sync::Arc;
+        use trace::{TraceEvent, TraceEventCodes};
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let trace_events = events.clone();
+        let db = Connection::open_in_memory().unwrap();
+        db.trace_v2(TraceEventCodes::STMT | TraceEventCodes::ROW,
+                    Some(move |evt: TraceEvent| {
+            let evt = match evt {
+                TraceEvent::Stmt(sql) => sql.to_owned(),
+                TraceEvent::Row => "row".to_owned(),
+                other => panic!("unexpected {:?}", other),
+            };
+            trace_events.lock().unwrap().push(evt);
+        }));
+        let _ = db.query_row("SELECT ?", &[&1i32], |_| {});
+        db.trace_v2(TraceEventCodes::empty(), None::<fn(TraceEvent)>);
+        let _ = db.query_row("SELECT ?", &[&2i32], |_| {});
+
+        assert_eq!(*events.lock().unwrap(), vec!["SELECT 1", "row"]);
+    }
+
+    #[test]
+    #[cfg(any(feature = "bundled", feature = "modern_sqlite"))]
+    fn test_trace_v2_profile_and_close() {
+        use std::sync::Arc;
+        use trace::{TraceEvent, TraceEventCodes};
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let trace_events = events.clone();
+        let db = Connection::open_in_memory().unwrap();
+        db.trace_v2(TraceEventCodes::PROFILE | TraceEventCodes::CLOSE,
+                    Some(move |evt: TraceEvent| {
+            let evt = match evt {
+                TraceEvent::Profile(sql, _) => sql.to_owned(),
+                TraceEvent::Close => "close".to_owned(),
+                other => panic!("unexpected {:?}", other),
+            };
+            trace_events.lock().unwrap().push(evt);
+        }));
+        db.execute_batch("PRAGMA application_id = 1").unwrap();
+        db.close().unwrap();
+
+        assert_eq!(*events.lock().unwrap(), vec!["PRAGMA application_id = 1", "close"]);
+    }
+
+    #[test]
+    #[cfg(feature = "log")]
+    fn test_trace_to_log_crate() {
+        use trace::log_crate::{self, Log, Metadata, Record};
+
+        lazy_static! {
+            static ref LOGGED: Mutex<Vec<(log_crate::Level, String)>> = Mutex::new(Vec::new());
+        }
+        struct Logger;
+        impl Log for Logger {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+            fn log(&self, record: &Record) {
+                let msg = record.args().to_string();
+                LOGGED.lock().unwrap().push((record.level(), msg));
+            }
+            fn flush(&self) {}
+        }
+        static LOGGER: Logger = Logger;
+        log_crate::set_logger(&LOGGER).unwrap();
+        log_crate::set_max_level(log_crate::LevelFilter::Trace);
+
+        let db = Connection::open_in_memory().unwrap();
+        db.trace_to_log_crate();
+        let _ = db.query_row("SELECT ?", &[&1i32], |_| {});
+
+        let logged = LOGGED.lock().unwrap();
+        assert_eq!(2, logged.len());
+        assert_eq!((log_crate::Level::Trace, ":memory:: SELECT 1".to_owned()), logged[0]);
+        assert_eq!(log_crate::Level::Debug, logged[1].0);
+        assert!(logged[1].1.starts_with(":memory:: SELECT ? ("));
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    fn test_trace_to_tracing() {
+        use std::fmt;
+        use std::sync::Arc;
+        use trace::tracing_crate::{self, span, Event, Metadata, Subscriber};
+        use trace::tracing_crate::field::{Field, Visit};
+
+        struct Fields(Vec<String>);
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &fmt::Debug) {
+                self.0.push(format!("{}={:?}", field.name(), value));
+            }
+        }
+
+        struct Recorder(Arc<Mutex<Vec<(tracing_crate::Level, Vec<String>)>>>);
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+            fn new_span(&self, _: &span::Attributes) -> span::Id {
+                span::Id::from_u64(1)
+            }
+            fn record(&self, _: &span::Id, _: &span::Record) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, event: &Event) {
+                let mut fields = Fields(Vec::new());
+                event.record(&mut fields);
+                self.0.lock().unwrap().push((*event.metadata().level(), fields.0));
+            }
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Recorder(events.clone());
+        tracing_crate::subscriber::with_default(recorder, || {
+            let db = Connection::open_in_memory().unwrap();
+            db.trace_to_tracing();
+            let _ = db.query_row("SELECT ?", &[&1i32], |_| {});
+        });
+
+        let events = events.lock().unwrap();
+        assert_eq!(2, events.len());
+        assert_eq!(tracing_crate::Level::TRACE, events[0].0);
+        assert_eq!(vec!["message=statement started", "path=\":memory:\"", "sql=\"SELECT 1\""],
+                   events[0].1);
+        assert_eq!(tracing_crate::Level::DEBUG, events[1].0);
+        assert!(events[1].1.iter().any(|f| f.starts_with("elapsed_us=")));
+    }
 }
@@ -431,7 +431,7 @@ mod test {
         {
             let tx = db.transaction().unwrap();
             assert_eq!(2i32,
-                       tx.query_row::<i32, _>("SELECT SUM(x) FROM foo", &[], |r| r.get(0))
+                       tx.query_row::<i32, _, _>("SELECT SUM(x) FROM foo", &[], |r| r.get(0))
                            .unwrap());
         }
     }
@@ -458,7 +458,7 @@ mod test {
         {
             let tx = db.transaction().unwrap();
             assert_eq!(6i32,
-                       tx.query_row::<i32, _>("SELECT SUM(x) FROM foo", &[], |r| r.get(0))
+                       tx.query_row::<i32, _, _>("SELECT SUM(x) FROM foo", &[], |r| r.get(0))
                            .unwrap());
         }
     }
@@ -553,7 +553,7 @@ mod test {
     }
 
     fn assert_current_sum(x: i32, conn: &Connection) {
-        let i = conn.query_row::<i32, _>("SELECT SUM(x) FROM foo", &[], |r| r.get(0))
+        let i = conn.query_row::<i32, _, _>("SELECT SUM(x) FROM foo", &[], |r| r.get(0))
             .unwrap();
         assert_eq!(x, i);
     }
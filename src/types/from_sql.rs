@@ -148,9 +148,85 @@ impl FromSql for Value {
     }
 }
 
+/// A trait for types that can be created from a SQLite value borrowed for the
+/// lifetime `'a`, like `&'a str` and `&'a [u8]`.
+///
+/// `Row::get` and `Row::get_checked` accept any type implementing this trait,
+/// which includes all the types implementing `FromSql`. Borrowed values are
+/// tied to the lifetime of the row, and read without copying the text or blob
+/// returned by SQLite:
+///
+/// ```rust
+/// use rusqlite::{Connection, Result};
+///
+/// fn total_length(conn: &Connection) -> Result<usize> {
+///     let mut stmt = try!(conn.prepare("SELECT body FROM document"));
+///     let mut rows = try!(stmt.query(&[]));
+///     let mut total = 0;
+///     while let Some(row) = rows.next() {
+///         let row = try!(row);
+///         let body: &str = try!(row.get_checked(0));
+///         total += body.len();
+///     }
+///     Ok(total)
+/// }
+/// ```
+pub trait FromSqlRef<'a>: Sized {
+    fn column_result_ref(value: ValueRef<'a>) -> FromSqlResult<Self>;
+}
+
+impl<'a, T: FromSql> FromSqlRef<'a> for T {
+    fn column_result_ref(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        T::column_result(value)
+    }
+}
+
+impl<'a> FromSqlRef<'a> for &'a str {
+    fn column_result_ref(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Text(t) => Ok(t),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl<'a> FromSqlRef<'a> for &'a [u8] {
+    fn column_result_ref(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Blob(b) => Ok(b),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl<'a> FromSqlRef<'a> for Option<&'a str> {
+    fn column_result_ref(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Null => Ok(None),
+            _ => FromSqlRef::column_result_ref(value).map(Some),
+        }
+    }
+}
+
+impl<'a> FromSqlRef<'a> for Option<&'a [u8]> {
+    fn column_result_ref(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Null => Ok(None),
+            _ => FromSqlRef::column_result_ref(value).map(Some),
+        }
+    }
+}
+
+impl<'a> FromSqlRef<'a> for ValueRef<'a> {
+    fn column_result_ref(value: ValueRef<'a>) -> FromSqlResult<Self> {
+        Ok(value)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use {Connection, Error};
+    use types::{Type, ValueRef};
     use super::FromSql;
 
     fn checked_memory_handle() -> Connection {
@@ -190,4 +266,44 @@ mod test {
         check_ranges::<u16>(&db, &[-2, -1, 65536], &[0, 1, 65535]);
         check_ranges::<u32>(&db, &[-2, -1, 4294967296], &[0, 1, 4294967295]);
     }
+
+    #[test]
+    fn test_borrowed() {
+        let db = checked_memory_handle();
+        let mut stmt = db.prepare("SELECT 'text', x'0102', NULL, 1").unwrap();
+        let mut rows = stmt.query(&[]).unwrap();
+        let row = rows.next().unwrap().unwrap();
+
+        let text: &str = row.get(0);
+        let blob: &[u8] = row.get(1);
+        assert_eq!("text", text);
+        assert_eq!(&[1, 2], blob);
+        assert_eq!(None, row.get::<_, Option<&str>>(2));
+        assert_eq!(Some(&[1u8, 2][..]), row.get::<_, Option<&[u8]>>(1));
+        assert_eq!(ValueRef::Integer(1), row.get::<_, ValueRef>(3));
+
+        match row.get_checked::<_, &str>(1) {
+            Err(Error::InvalidColumnType(1, Type::Blob)) => {}
+            r => panic!("Unexpected result {:?}", r),
+        }
+        match row.get_checked::<_, &[u8]>(3) {
+            Err(Error::InvalidColumnType(3, Type::Integer)) => {}
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_get_ref() {
+        let db = checked_memory_handle();
+        let mut stmt = db.prepare("SELECT 'text' AS t, 1.5 AS r").unwrap();
+        let mut rows = stmt.query(&[]).unwrap();
+        let row = rows.next().unwrap().unwrap();
+
+        assert_eq!(ValueRef::Text("text"), row.get_ref(0).unwrap());
+        assert_eq!(ValueRef::Real(1.5), row.get_ref("r").unwrap());
+        match row.get_ref(2) {
+            Err(Error::InvalidColumnIndex(2)) => {}
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
 }
@@ -50,8 +50,11 @@
 //! `ToSql` and `FromSql` are also implemented for `Option<T>` where `T` implements `ToSql` or
 //! `FromSql` for the cases where you want to know if a value was NULL (which gets translated to
 //! `None`).
+//!
+//! `&str` and `&[u8]` can be read from a row without copying them through the `FromSqlRef`
+//! trait, and `Row::get_ref` gives access to the raw `ValueRef`.
 
-pub use self::from_sql::{FromSql, FromSqlError, FromSqlResult};
+pub use self::from_sql::{FromSql, FromSqlRef, FromSqlError, FromSqlResult};
 pub use self::to_sql::{ToSql, ToSqlOutput};
 pub use self::value::Value;
 pub use self::value_ref::ValueRef;
@@ -184,7 +187,7 @@ mod test {
             .unwrap();
 
         assert_eq!(10i64,
-                   db.query_row::<i64, _>("SELECT i FROM foo", &[], |r| r.get(0))
+                   db.query_row::<i64, _, _>("SELECT i FROM foo", &[], |r| r.get(0))
                        .unwrap());
     }
 
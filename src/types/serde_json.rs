@@ -44,7 +44,7 @@ mod test {
         let json = r#"{"foo": 13, "bar": "baz"}"#;
         let data: serde_json::Value = serde_json::from_str(json).unwrap();
         db.execute("INSERT INTO foo (t, b) VALUES (?, ?)",
-                     &[&data, &json.as_bytes()])
+                     params![data, json.as_bytes()])
             .unwrap();
 
         let t: serde_json::Value = db.query_row("SELECT t FROM foo", &[], |r| r.get(0))
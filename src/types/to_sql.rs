@@ -1,4 +1,6 @@
 use super::{Null, Value, ValueRef};
+#[cfg(feature = "array")]
+use vtab::array::Array;
 use Result;
 
 /// `ToSqlOutput` represents the possible output types for implementors of the `ToSql` trait.
@@ -13,6 +15,11 @@ pub enum ToSqlOutput<'a> {
     /// A BLOB of the given length that is filled with zeroes.
     #[cfg(feature = "blob")]
     ZeroBlob(i32),
+
+    /// An array of values, bound as a pointer for use with the `rarray`
+    /// table-valued function.
+    #[cfg(feature = "array")]
+    Array(Array),
 }
 
 impl<'a, T: ?Sized> From<&'a T> for ToSqlOutput<'a>
@@ -37,6 +44,8 @@ impl<'a> ToSql for ToSqlOutput<'a> {
 
                #[cfg(feature = "blob")]
             ToSqlOutput::ZeroBlob(i) => ToSqlOutput::ZeroBlob(i),
+               #[cfg(feature = "array")]
+            ToSqlOutput::Array(ref a) => ToSqlOutput::Array(a.clone()),
            })
     }
 }
@@ -80,11 +89,15 @@ to_sql_self!(u16);
 to_sql_self!(u32);
 to_sql_self!(f64);
 
-impl<'a, T: ?Sized> ToSql for &'a T
-    where &'a T: Into<ToSqlOutput<'a>>
-{
+impl<'a, T: ToSql + ?Sized> ToSql for &'a T {
     fn to_sql(&self) -> Result<ToSqlOutput> {
-        Ok((*self).into())
+        (*self).to_sql()
+    }
+}
+
+impl<T: ToSql + ?Sized> ToSql for Box<T> {
+    fn to_sql(&self) -> Result<ToSqlOutput> {
+        (**self).to_sql()
     }
 }
 
@@ -143,4 +156,13 @@ mod test {
         is_to_sql::<u16>();
         is_to_sql::<u32>();
     }
+
+    #[test]
+    fn test_references_and_boxes() {
+        is_to_sql::<&i32>();
+        is_to_sql::<&&str>();
+        is_to_sql::<&ToSql>();
+        is_to_sql::<Box<ToSql>>();
+        is_to_sql::<Box<[u8]>>();
+    }
 }
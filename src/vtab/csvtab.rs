@@ -0,0 +1,381 @@
+//! CSV Virtual Table.
+//!
+//! Port of [csv](http://www.sqlite.org/cgi/src/finfo?name=ext/misc/csv.c) C extension.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use rusqlite::{Connection, Result};
+//! # use rusqlite::vtab::csvtab;
+//! fn example(db: &Connection) -> Result<()> {
+//!     // Note: This should be done once (usually when opening the DB).
+//!     try!(csvtab::load_module(&db));
+//!     try!(db.execute_batch("CREATE VIRTUAL TABLE vtab USING csv(filename='test.csv', header=yes)"));
+//!     // Columns are named after the header row.
+//!     try!(db.execute_batch("CREATE TABLE partners AS SELECT * FROM vtab"));
+//!     Ok(())
+//! }
+//! ```
+extern crate csv;
+
+use std::fs::File;
+use std::os::raw::c_int;
+use std::path::Path;
+use std::result;
+use std::str;
+
+use types::Null;
+use vtab::{dequote, escape_double_quote, parse_boolean, read_only_module, Context, CreateVTab,
+           IndexInfo, VTab, VTabCursor, Values};
+use {Connection, Error, Result};
+
+/// Register the "csv" module.
+///
+/// ```sql
+/// CREATE VIRTUAL TABLE vtab USING csv(
+///   filename=FILENAME -- Name of file containing CSV content
+///   [, header=YES|NO] -- First row of CSV defines the names of columns if "yes". Default "no".
+///   [, delimiter=C] -- A single character used to separate fields. Default ",".
+///   [, quote=C] -- A single character used to quote fields. Default '"'.
+/// );
+/// ```
+pub fn load_module(conn: &Connection) -> Result<()> {
+    let aux: Option<()> = None;
+    conn.create_module("csv", read_only_module::<CSVTab>(), aux)
+}
+
+/// An instance of the CSV virtual table
+struct CSVTab {
+    /// Name of the CSV file
+    filename: String,
+    has_headers: bool,
+    delimiter: u8,
+    quote: u8,
+    /// Offset to start of data
+    offset_first_row: csv::Position,
+}
+
+impl CSVTab {
+    fn reader(&self) -> result::Result<csv::Reader<File>, csv::Error> {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .flexible(true)
+            .from_path(&self.filename)
+    }
+
+    fn parameter(c_slice: &[u8]) -> Result<(&str, &str)> {
+        let arg = try!(str::from_utf8(c_slice)).trim();
+        let mut split = arg.splitn(2, '=');
+        if let Some(key) = split.next() {
+            if let Some(value) = split.next() {
+                let param = key.trim();
+                let value = dequote(value.trim());
+                return Ok((param, value));
+            }
+        }
+        Err(Error::ModuleError(format!("illegal argument: '{}'", arg)))
+    }
+
+    fn parse_byte(arg: &str) -> Option<u8> {
+        if arg.len() == 1 {
+            arg.bytes().next()
+        } else {
+            None
+        }
+    }
+}
+
+impl VTab for CSVTab {
+    type Aux = ();
+    type Cursor = CSVTabCursor;
+
+    fn connect(_: Option<&()>, args: &[&[u8]]) -> Result<(String, CSVTab)> {
+        if args.len() < 4 {
+            return Err(Error::ModuleError("no CSV file specified".to_owned()));
+        }
+
+        let mut vtab = CSVTab {
+            filename: "".to_owned(),
+            has_headers: false,
+            delimiter: b',',
+            quote: b'"',
+            offset_first_row: csv::Position::new(),
+        };
+
+        let args = &args[3..];
+        for c_slice in args {
+            let (param, value) = try!(CSVTab::parameter(c_slice));
+            match param {
+                "filename" => {
+                    if !Path::new(value).exists() {
+                        return Err(Error::ModuleError(format!("file '{}' does not exist", value)));
+                    }
+                    vtab.filename = value.to_owned();
+                }
+                "header" => {
+                    if let Some(b) = parse_boolean(value) {
+                        vtab.has_headers = b;
+                    } else {
+                        return Err(Error::ModuleError(format!("unrecognized argument to 'header': {}",
+                                                              value)));
+                    }
+                }
+                "delimiter" => {
+                    if let Some(b) = CSVTab::parse_byte(value) {
+                        vtab.delimiter = b;
+                    } else {
+                        return Err(Error::ModuleError(format!("unrecognized argument to 'delimiter': {}",
+                                                              value)));
+                    }
+                }
+                "quote" => {
+                    if let Some(b) = CSVTab::parse_byte(value) {
+                        vtab.quote = b;
+                    } else {
+                        return Err(Error::ModuleError(format!("unrecognized argument to 'quote': {}",
+                                                              value)));
+                    }
+                }
+                _ => {
+                    return Err(Error::ModuleError(format!("unrecognized parameter '{}'", param)));
+                }
+            }
+        }
+
+        if vtab.filename.is_empty() {
+            return Err(Error::ModuleError("no CSV file specified".to_owned()));
+        }
+
+        let mut reader = try!(vtab.reader());
+        let mut record = csv::StringRecord::new();
+        try!(reader.read_record(&mut record));
+        let cols: Vec<String> = if vtab.has_headers {
+            vtab.offset_first_row = reader.position().clone();
+            record.iter().map(|s| s.to_owned()).collect()
+        } else {
+            (1..record.len() + 1).map(|i| format!("c{}", i)).collect()
+        };
+
+        if cols.is_empty() {
+            return Err(Error::ModuleError("no columns specified".to_owned()));
+        }
+
+        let mut schema = String::from("CREATE TABLE x(");
+        for (i, col) in cols.iter().enumerate() {
+            if i > 0 {
+                schema.push_str(", ");
+            }
+            schema.push('"');
+            schema.push_str(&escape_double_quote(col));
+            schema.push_str("\" TEXT");
+        }
+        schema.push(')');
+
+        Ok((schema, vtab))
+    }
+
+    // Only a forward full table scan is supported.
+    fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
+        info.set_estimated_cost(1_000_000.);
+        Ok(())
+    }
+
+    fn open(&self) -> Result<CSVTabCursor> {
+        Ok(CSVTabCursor::new(try!(self.reader()), self.offset_first_row.clone()))
+    }
+}
+
+impl CreateVTab for CSVTab {}
+
+/// A cursor for the CSV virtual table
+struct CSVTabCursor {
+    /// The CSV reader object
+    reader: csv::Reader<File>,
+    /// Offset to start of data
+    offset_first_row: csv::Position,
+    /// Current cursor position used as rowid
+    row_number: usize,
+    /// Values of the current row
+    cols: csv::StringRecord,
+    eof: bool,
+}
+
+impl CSVTabCursor {
+    fn new(reader: csv::Reader<File>, offset_first_row: csv::Position) -> CSVTabCursor {
+        CSVTabCursor {
+            reader: reader,
+            offset_first_row: offset_first_row,
+            row_number: 0,
+            cols: csv::StringRecord::new(),
+            eof: false,
+        }
+    }
+}
+
+impl VTabCursor for CSVTabCursor {
+    // Only a full table scan is supported. So `filter` simply rewinds to
+    // the beginning.
+    fn filter(&mut self, _idx_num: c_int, _args: &Values) -> Result<()> {
+        try!(self.reader.seek(self.offset_first_row.clone()));
+        self.row_number = 0;
+        self.eof = false;
+        self.next()
+    }
+
+    fn next(&mut self) -> Result<()> {
+        self.eof = !try!(self.reader.read_record(&mut self.cols));
+        self.row_number += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.eof
+    }
+
+    fn column(&self, ctx: &mut Context, col: c_int) -> Result<()> {
+        // Short rows yield NULL for their missing fields.
+        match self.cols.get(col as usize) {
+            Some(value) => ctx.set_result(&value),
+            None => ctx.set_result(&Null),
+        }
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.row_number as i64)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Error {
+        Error::ModuleError(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate tempdir;
+
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::Path;
+
+    use self::tempdir::TempDir;
+    use vtab::csvtab;
+    use {Connection, Error, Result};
+
+    fn write_csv(dir: &TempDir, name: &str, content: &str) -> String {
+        let path = dir.path().join(name);
+        let mut file = File::create(&path).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        path.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn test_csv_module() {
+        let temp_dir = TempDir::new("test_csv_module").unwrap();
+        let path = write_csv(&temp_dir,
+                             "test.csv",
+                             "\"id\",\"colA\",\"colB\",\"colC\"\n\
+                              1,2,3,4\n\
+                              a,b,c,d\n\
+                              \"quoted, comma\",\"x\"\"y\",,z\n");
+
+        let db = Connection::open_in_memory().unwrap();
+        csvtab::load_module(&db).unwrap();
+        db.execute_batch(&format!("CREATE VIRTUAL TABLE vtab USING csv(filename='{}', header=yes)",
+                                  path))
+            .unwrap();
+
+        {
+            let mut s = db.prepare("SELECT rowid, * FROM vtab").unwrap();
+            {
+                let headers = s.column_names();
+                assert_eq!(vec!["rowid", "id", "colA", "colB", "colC"], headers);
+            }
+
+            let ids: Result<Vec<i32>> = s.query_map(&[], |row| row.get::<_, i32>(0))
+                .unwrap()
+                .collect();
+            let sum = ids.unwrap().iter().fold(0, |acc, &id| acc + id);
+            assert_eq!(sum, 6);
+
+            let values: Vec<(String, String)> = s.query_map(&[], |row| (row.get(1), row.get(3)))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect();
+            assert_eq!(values,
+                       vec![("1".to_owned(), "3".to_owned()),
+                            ("a".to_owned(), "c".to_owned()),
+                            ("quoted, comma".to_owned(), "".to_owned())]);
+        }
+
+        let quoted: String = db.query_row("SELECT colA FROM vtab WHERE colC = 'z'",
+                                          &[],
+                                          |row| row.get(0))
+            .unwrap();
+        assert_eq!("x\"y", quoted);
+
+        db.execute_batch("CREATE TABLE t AS SELECT * FROM vtab;
+                          DROP TABLE vtab;")
+            .unwrap();
+        let count: i64 = db.query_row("SELECT COUNT(*) FROM t", &[], |row| row.get(0))
+            .unwrap();
+        assert_eq!(3, count);
+    }
+
+    #[test]
+    fn test_csv_options() {
+        let temp_dir = TempDir::new("test_csv_options").unwrap();
+        let path = write_csv(&temp_dir, "test.psv", "'a|b'|1\nc|2|extra\nd\n");
+
+        let db = Connection::open_in_memory().unwrap();
+        csvtab::load_module(&db).unwrap();
+        db.execute_batch(&format!("CREATE VIRTUAL TABLE vtab USING csv(filename='{}', \
+                                   delimiter='|', quote=\"'\")",
+                                  path))
+            .unwrap();
+
+        let mut s = db.prepare("SELECT * FROM vtab").unwrap();
+        assert_eq!(vec!["c1", "c2"], s.column_names());
+        let rows: Vec<(String, Option<String>)> = s.query_map(&[], |row| (row.get(0), row.get(1)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows,
+                   vec![("a|b".to_owned(), Some("1".to_owned())),
+                        ("c".to_owned(), Some("2".to_owned())),
+                        ("d".to_owned(), None)]);
+    }
+
+    #[test]
+    fn test_csv_value_with_equal_sign() {
+        let temp_dir = TempDir::new("test_csv_value_with_equal_sign").unwrap();
+        let path = write_csv(&temp_dir, "a=b.csv", "x,y\n1,2\n");
+
+        let db = Connection::open_in_memory().unwrap();
+        csvtab::load_module(&db).unwrap();
+        db.execute_batch(&format!("CREATE VIRTUAL TABLE vtab USING csv(filename = '{}', \
+                                   header=yes)",
+                                  path))
+            .unwrap();
+        let sum: i64 = db.query_row("SELECT x + y FROM vtab", &[], |row| row.get(0)).unwrap();
+        assert_eq!(3, sum);
+    }
+
+    #[test]
+    fn test_csv_invalid_arguments() {
+        let db = Connection::open_in_memory().unwrap();
+        csvtab::load_module(&db).unwrap();
+
+        assert!(!Path::new("does-not-exist.csv").exists());
+        for args in &["", "filename='does-not-exist.csv'", "filename", "unknown=1"] {
+            let sql = format!("CREATE VIRTUAL TABLE vtab USING csv({})", args);
+            match db.execute_batch(&sql) {
+                Err(Error::SqliteFailure(_, Some(_))) => (),
+                r => panic!("Unexpected result {:?} for {}", r, args),
+            }
+        }
+    }
+}
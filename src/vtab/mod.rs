@@ -0,0 +1,1265 @@
+//! Create virtual tables.
+//!
+//! Follow these steps to create your own virtual table:
+//!
+//! 1. Implement the `VTab` and `VTabCursor` traits (and `CreateVTab` if the
+//!    table can be created with `CREATE VIRTUAL TABLE`).
+//! 2. Build a `Module` for your `VTab` implementation with `read_only_module`
+//!    or `eponymous_only_module`.
+//! 3. Register the `Module` with `Connection::create_module`.
+//! 4. Run a `CREATE VIRTUAL TABLE` command that specifies the new module in
+//!    the `USING` clause, or query an eponymous-only module directly by name.
+//!
+//! (See [SQLite doc](http://sqlite.org/vtab.html))
+#[cfg(feature = "csvtab")]
+use std::borrow::Cow::{self, Borrowed, Owned};
+use std::ffi::CStr;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use context::set_result;
+use error::error_from_handle;
+use ffi;
+use types::{FromSql, FromSqlError, ToSql, ValueRef};
+use {str_to_cstring, Connection, Error, InnerConnection, Result};
+
+#[cfg(feature = "array")]
+pub mod array;
+#[cfg(feature = "csvtab")]
+pub mod csvtab;
+#[cfg(feature = "series")]
+pub mod series;
+
+// let conn: Connection = ...;
+// let mod: Module = ...; // VTab builder
+// conn.create_module("module", mod);
+//
+// conn.execute("CREATE VIRTUAL TABLE foo USING module(...)");
+// \-> Module::xcreate
+//  |-> let vtab: VTab = ...; // on the heap
+//  \-> conn.declare_vtab("CREATE TABLE foo (...)");
+// conn = Connection::open(...);
+// \-> Module::xconnect
+//  |-> let vtab: VTab = ...; // on the heap
+//  \-> conn.declare_vtab("CREATE TABLE foo (...)");
+//
+// conn.close();
+// \-> vtab.xdisconnect
+// conn.execute("DROP TABLE foo");
+// \-> vtab.xDestroy
+//
+// let stmt = conn.prepare("SELECT ... FROM foo WHERE ...");
+// \-> vtab.xbestindex
+// stmt.query().next();
+// \-> vtab.xopen
+//  |-> let cursor: VTabCursor = ...; // on the heap
+//  |-> cursor.xfilter or xnext
+//  |-> cursor.xeof
+//  \-> if not eof { cursor.column or xrowid } else { cursor.xclose }
+//
+
+/// Virtual table module
+///
+/// (See [SQLite doc](https://sqlite.org/c3ref/module.html))
+pub struct Module<T: VTab> {
+    base: ffi::sqlite3_module,
+    phantom: PhantomData<T>,
+}
+
+/// Create a read-only virtual table implementation.
+///
+/// The module can be instantiated with `CREATE VIRTUAL TABLE ... USING`.
+///
+/// (See [SQLite doc](https://sqlite.org/vtab.html#creating_new_virtual_table_implementations))
+pub fn read_only_module<T: CreateVTab>() -> Module<T> {
+    let base = ffi::sqlite3_module {
+        iVersion: 1,
+        xCreate: Some(rust_create::<T>),
+        xConnect: Some(rust_connect::<T>),
+        xBestIndex: Some(rust_best_index::<T>),
+        xDisconnect: Some(rust_disconnect::<T>),
+        xDestroy: Some(rust_destroy::<T>),
+        xOpen: Some(rust_open::<T>),
+        xClose: Some(rust_close::<T::Cursor>),
+        xFilter: Some(rust_filter::<T::Cursor>),
+        xNext: Some(rust_next::<T::Cursor>),
+        xEof: Some(rust_eof::<T::Cursor>),
+        xColumn: Some(rust_column::<T::Cursor>),
+        xRowid: Some(rust_rowid::<T::Cursor>),
+        // The remaining methods (updates, transactions, renames, ...) are left
+        // unimplemented; their number depends on the SQLite version.
+        ..unsafe { mem::zeroed() }
+    };
+    Module {
+        base: base,
+        phantom: PhantomData::<T>,
+    }
+}
+
+/// Create an eponymous only virtual table implementation.
+///
+/// The module is used directly by name (e.g., `SELECT * FROM module_name`)
+/// and cannot be instantiated with `CREATE VIRTUAL TABLE`. Note: Eponymous
+/// virtual tables require SQLite 3.9.0 or later.
+///
+/// (See [SQLite doc](https://sqlite.org/vtab.html#eponymous_only_virtual_tables))
+pub fn eponymous_only_module<T: VTab>() -> Module<T> {
+    let base = ffi::sqlite3_module {
+        iVersion: 1,
+        // A null xCreate marks the module as eponymous-only.
+        xCreate: None,
+        xConnect: Some(rust_connect::<T>),
+        xBestIndex: Some(rust_best_index::<T>),
+        xDisconnect: Some(rust_disconnect::<T>),
+        xDestroy: Some(rust_disconnect::<T>),
+        xOpen: Some(rust_open::<T>),
+        xClose: Some(rust_close::<T::Cursor>),
+        xFilter: Some(rust_filter::<T::Cursor>),
+        xNext: Some(rust_next::<T::Cursor>),
+        xEof: Some(rust_eof::<T::Cursor>),
+        xColumn: Some(rust_column::<T::Cursor>),
+        xRowid: Some(rust_rowid::<T::Cursor>),
+        ..unsafe { mem::zeroed() }
+    };
+    Module {
+        base: base,
+        phantom: PhantomData::<T>,
+    }
+}
+
+/// Virtual table instance trait.
+///
+/// (See [SQLite doc](https://sqlite.org/c3ref/vtab.html))
+pub trait VTab: Sized {
+    /// Client data passed to `Connection::create_module`.
+    type Aux;
+    /// Specific cursor implementation
+    type Cursor: VTabCursor;
+
+    /// Establish a new connection to an existing virtual table.
+    ///
+    /// `args` are the module name, the database name, the table name and then
+    /// the module arguments (if any) given to `CREATE VIRTUAL TABLE`. Returns
+    /// the `CREATE TABLE` statement declaring the table's columns together with
+    /// the new table.
+    ///
+    /// (See [SQLite doc](https://sqlite.org/vtab.html#the_xconnect_method))
+    fn connect(aux: Option<&Self::Aux>, args: &[&[u8]]) -> Result<(String, Self)>;
+
+    /// Determine the best way to access the virtual table.
+    ///
+    /// (See [SQLite doc](https://sqlite.org/vtab.html#the_xbestindex_method))
+    fn best_index(&self, info: &mut IndexInfo) -> Result<()>;
+
+    /// Create a new cursor used for accessing a virtual table.
+    ///
+    /// (See [SQLite doc](https://sqlite.org/vtab.html#the_xopen_method))
+    fn open(&self) -> Result<Self::Cursor>;
+}
+
+/// Non-eponymous virtual table instance trait.
+///
+/// (See [SQLite doc](https://sqlite.org/c3ref/vtab.html))
+pub trait CreateVTab: VTab {
+    /// Create a new instance of a virtual table in response to a
+    /// `CREATE VIRTUAL TABLE` statement. By default, this is the same as
+    /// `connect`.
+    ///
+    /// (See [SQLite doc](https://sqlite.org/vtab.html#the_xcreate_method))
+    fn create(aux: Option<&Self::Aux>, args: &[&[u8]]) -> Result<(String, Self)> {
+        Self::connect(aux, args)
+    }
+
+    /// Destroy the underlying table implementation in response to a
+    /// `DROP TABLE` statement. By default, there is nothing to do.
+    ///
+    /// (See [SQLite doc](https://sqlite.org/vtab.html#the_xdestroy_method))
+    fn destroy(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Index constraint operator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndexConstraintOp {
+    Eq,
+    Gt,
+    Le,
+    Lt,
+    Ge,
+    Match,
+    Like,
+    Glob,
+    Regexp,
+    Ne,
+    IsNot,
+    IsNotNull,
+    IsNull,
+    Is,
+    /// An overloaded function (see `xFindFunction`) with the given operator code.
+    Function(u8),
+    /// An operator unknown to this version of rusqlite.
+    Unknown(u8),
+}
+
+impl From<u8> for IndexConstraintOp {
+    fn from(code: u8) -> IndexConstraintOp {
+        match code {
+            2 => IndexConstraintOp::Eq,
+            4 => IndexConstraintOp::Gt,
+            8 => IndexConstraintOp::Le,
+            16 => IndexConstraintOp::Lt,
+            32 => IndexConstraintOp::Ge,
+            64 => IndexConstraintOp::Match,
+            65 => IndexConstraintOp::Like,
+            66 => IndexConstraintOp::Glob,
+            67 => IndexConstraintOp::Regexp,
+            68 => IndexConstraintOp::Ne,
+            69 => IndexConstraintOp::IsNot,
+            70 => IndexConstraintOp::IsNotNull,
+            71 => IndexConstraintOp::IsNull,
+            72 => IndexConstraintOp::Is,
+            c if c >= 150 => IndexConstraintOp::Function(c),
+            c => IndexConstraintOp::Unknown(c),
+        }
+    }
+}
+
+/// Pass information into and receive the reply from the `VTab.best_index` method.
+///
+/// (See [SQLite doc](http://sqlite.org/c3ref/index_info.html))
+pub struct IndexInfo(*mut ffi::sqlite3_index_info);
+
+impl IndexInfo {
+    /// Record WHERE clause constraints.
+    pub fn constraints(&self) -> IndexConstraintIter {
+        let constraints = unsafe {
+            let n = (*self.0).nConstraint as usize;
+            if n == 0 {
+                &[]
+            } else {
+                slice::from_raw_parts((*self.0).aConstraint, n)
+            }
+        };
+        IndexConstraintIter { iter: constraints.iter() }
+    }
+
+    /// Information about the ORDER BY clause.
+    pub fn order_bys(&self) -> OrderByIter {
+        let order_bys = unsafe {
+            let n = (*self.0).nOrderBy as usize;
+            if n == 0 {
+                &[]
+            } else {
+                slice::from_raw_parts((*self.0).aOrderBy, n)
+            }
+        };
+        OrderByIter { iter: order_bys.iter() }
+    }
+
+    /// Number of terms in the ORDER BY clause
+    pub fn num_of_order_by(&self) -> usize {
+        unsafe { (*self.0).nOrderBy as usize }
+    }
+
+    /// Information about what parameters to pass to `VTabCursor.filter` for
+    /// the `constraint_idx`th constraint.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `constraint_idx` is not less than the number of constraints.
+    pub fn constraint_usage(&mut self, constraint_idx: usize) -> IndexConstraintUsage {
+        let constraint_usages = unsafe {
+            let n = (*self.0).nConstraint as usize;
+            if n == 0 {
+                &mut []
+            } else {
+                slice::from_raw_parts_mut((*self.0).aConstraintUsage, n)
+            }
+        };
+        IndexConstraintUsage(&mut constraint_usages[constraint_idx])
+    }
+
+    /// Number used to identify the index
+    pub fn set_idx_num(&mut self, idx_num: c_int) {
+        unsafe {
+            (*self.0).idxNum = idx_num;
+        }
+    }
+
+    /// True if output is already ordered
+    pub fn set_order_by_consumed(&mut self, order_by_consumed: bool) {
+        unsafe {
+            (*self.0).orderByConsumed = if order_by_consumed { 1 } else { 0 };
+        }
+    }
+
+    /// Estimated cost of using this index
+    pub fn set_estimated_cost(&mut self, estimated_cost: f64) {
+        unsafe {
+            (*self.0).estimatedCost = estimated_cost;
+        }
+    }
+}
+
+/// Iterator over the WHERE clause constraints of an `IndexInfo`.
+pub struct IndexConstraintIter<'a> {
+    iter: slice::Iter<'a, ffi::sqlite3_index_info_sqlite3_index_constraint>,
+}
+
+impl<'a> Iterator for IndexConstraintIter<'a> {
+    type Item = IndexConstraint<'a>;
+
+    fn next(&mut self) -> Option<IndexConstraint<'a>> {
+        self.iter.next().map(|raw| IndexConstraint(raw))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// WHERE clause constraint
+pub struct IndexConstraint<'a>(&'a ffi::sqlite3_index_info_sqlite3_index_constraint);
+
+impl<'a> IndexConstraint<'a> {
+    /// Column constrained.  -1 for ROWID
+    pub fn column(&self) -> c_int {
+        self.0.iColumn
+    }
+
+    /// Constraint operator
+    pub fn operator(&self) -> IndexConstraintOp {
+        IndexConstraintOp::from(self.0.op)
+    }
+
+    /// True if this constraint is usable
+    pub fn is_usable(&self) -> bool {
+        self.0.usable != 0
+    }
+}
+
+/// Information about what parameters to pass to `VTabCursor.filter`.
+pub struct IndexConstraintUsage<'a>(&'a mut ffi::sqlite3_index_info_sqlite3_index_constraint_usage);
+
+impl<'a> IndexConstraintUsage<'a> {
+    /// if `argv_index` > 0, constraint is part of argv to `VTabCursor.filter`
+    pub fn set_argv_index(&mut self, argv_index: c_int) {
+        self.0.argvIndex = argv_index;
+    }
+
+    /// if `omit`, do not code a test for this constraint
+    pub fn set_omit(&mut self, omit: bool) {
+        self.0.omit = if omit { 1 } else { 0 };
+    }
+}
+
+/// Iterator over the ORDER BY terms of an `IndexInfo`.
+pub struct OrderByIter<'a> {
+    iter: slice::Iter<'a, ffi::sqlite3_index_info_sqlite3_index_orderby>,
+}
+
+impl<'a> Iterator for OrderByIter<'a> {
+    type Item = OrderBy<'a>;
+
+    fn next(&mut self) -> Option<OrderBy<'a>> {
+        self.iter.next().map(|raw| OrderBy(raw))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// A column of the ORDER BY clause.
+pub struct OrderBy<'a>(&'a ffi::sqlite3_index_info_sqlite3_index_orderby);
+
+impl<'a> OrderBy<'a> {
+    /// Column number
+    pub fn column(&self) -> c_int {
+        self.0.iColumn
+    }
+
+    /// True for DESC.  False for ASC.
+    pub fn is_order_by_desc(&self) -> bool {
+        self.0.desc != 0
+    }
+}
+
+/// Virtual table cursor trait.
+///
+/// (See [SQLite doc](https://sqlite.org/c3ref/vtab_cursor.html))
+pub trait VTabCursor: Sized {
+    /// Begin a search of a virtual table. `idx_num` is the value set by
+    /// `VTab.best_index`, and `args` are the right-hand sides of the
+    /// constraints for which it set an `argv_index`, in that order.
+    ///
+    /// (See [SQLite doc](https://sqlite.org/vtab.html#the_xfilter_method))
+    fn filter(&mut self, idx_num: c_int, args: &Values) -> Result<()>;
+
+    /// Advance cursor to the next row of a result set initiated by `filter`.
+    ///
+    /// (See [SQLite doc](https://sqlite.org/vtab.html#the_xnext_method))
+    fn next(&mut self) -> Result<()>;
+
+    /// Must return `false` if the cursor currently points to a valid row of
+    /// data, or `true` otherwise.
+    ///
+    /// (See [SQLite doc](https://sqlite.org/vtab.html#the_xeof_method))
+    fn eof(&self) -> bool;
+
+    /// Find the value for the `i`-th column of the current row. `i` is
+    /// zero-based so the first column is numbered 0. May return its result
+    /// back to SQLite using one of the specified `ctx`.
+    ///
+    /// (See [SQLite doc](https://sqlite.org/vtab.html#the_xcolumn_method))
+    fn column(&self, ctx: &mut Context, i: c_int) -> Result<()>;
+
+    /// Return the rowid of row that the cursor is currently pointing at.
+    ///
+    /// (See [SQLite doc](https://sqlite.org/vtab.html#the_xrowid_method))
+    fn rowid(&self) -> Result<i64>;
+}
+
+/// Context is used by `VTabCursor.column` to specify the cell value.
+pub struct Context(*mut ffi::sqlite3_context);
+
+impl Context {
+    /// Set the value of the current cell.
+    pub fn set_result<T: ToSql>(&mut self, value: &T) -> Result<()> {
+        let t = try!(value.to_sql());
+        set_result(self.0, &t);
+        Ok(())
+    }
+}
+
+/// Wrapper to `VTabCursor.filter` arguments, the values requested by
+/// `VTab.best_index`.
+pub struct Values<'a> {
+    args: &'a [*mut ffi::sqlite3_value],
+}
+
+impl<'a> Values<'a> {
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.args.len()
+    }
+
+    /// Returns `true` if there is no value.
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    /// Returns the `idx`th value as a `T`.
+    ///
+    /// # Failure
+    ///
+    /// Will panic if `idx` is greater than or equal to `self.len()`.
+    ///
+    /// Will return Err if the underlying SQLite type cannot be converted to a `T`.
+    pub fn get<T: FromSql>(&self, idx: usize) -> Result<T> {
+        let arg = self.args[idx];
+        let value = unsafe { ValueRef::from_value(arg) };
+        FromSql::column_result(value).map_err(|err| match err {
+            FromSqlError::InvalidType => Error::InvalidFilterParameterType(idx, value.data_type()),
+            FromSqlError::OutOfRange(i) => Error::IntegralValueOutOfRange(idx as c_int, i),
+            FromSqlError::Other(err) => {
+                Error::FromSqlConversionFailure(idx, value.data_type(), err)
+            }
+        })
+    }
+
+    // `sqlite3_value_type` returns `SQLITE_NULL` for pointer.
+    // So it seems not possible to enhance `ValueRef::from_value`.
+    #[cfg(feature = "array")]
+    fn get_array(&self, idx: usize) -> Result<Option<array::Array>> {
+        use types::Value;
+        let arg = self.args[idx];
+        let ptr = unsafe { ffi::sqlite3_value_pointer(arg, array::ARRAY_TYPE) };
+        if ptr.is_null() {
+            Ok(None)
+        } else {
+            // SQLite still owns its reference: take a new one without
+            // releasing the original.
+            Ok(Some(unsafe {
+                        let rc = array::Array::from_raw(ptr as *const Vec<Value>);
+                        let array = rc.clone();
+                        mem::forget(rc);
+                        array
+                    }))
+        }
+    }
+
+    /// Turns `Values` into an iterator over the values as `ValueRef`s.
+    pub fn iter(&self) -> ValueIter<'a> {
+        ValueIter { iter: self.args.iter() }
+    }
+}
+
+impl<'a> IntoIterator for &'a Values<'a> {
+    type Item = ValueRef<'a>;
+    type IntoIter = ValueIter<'a>;
+
+    fn into_iter(self) -> ValueIter<'a> {
+        self.iter()
+    }
+}
+
+/// Iterator over the values of a `Values`.
+pub struct ValueIter<'a> {
+    iter: slice::Iter<'a, *mut ffi::sqlite3_value>,
+}
+
+impl<'a> Iterator for ValueIter<'a> {
+    type Item = ValueRef<'a>;
+
+    fn next(&mut self) -> Option<ValueRef<'a>> {
+        self.iter
+            .next()
+            .map(|&raw| unsafe { ValueRef::from_value(raw) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl Connection {
+    /// Register a virtual table implementation.
+    ///
+    /// `aux` is made available to `VTab::connect` (and `CreateVTab::create`)
+    /// for every instance of the module. The module remains registered until
+    /// the connection is closed or another module with the same name is
+    /// registered.
+    ///
+    /// (See [SQLite doc](https://sqlite.org/c3ref/create_module.html))
+    pub fn create_module<T>(&self,
+                            module_name: &str,
+                            module: Module<T>,
+                            aux: Option<T::Aux>)
+                            -> Result<()>
+        where T: VTab + 'static
+    {
+        self.db
+            .borrow_mut()
+            .create_module(module_name, module, aux)
+    }
+}
+
+// The module and its client data must outlive their registration, so they are
+// boxed together and handed to SQLite, which frees them through
+// `free_boxed_value` once the module is unregistered.
+struct ModuleData<T: VTab> {
+    module: Module<T>,
+    aux: Option<T::Aux>,
+}
+
+impl InnerConnection {
+    fn create_module<T>(&mut self,
+                        module_name: &str,
+                        module: Module<T>,
+                        aux: Option<T::Aux>)
+                        -> Result<()>
+        where T: VTab + 'static
+    {
+        let c_name = try!(str_to_cstring(module_name));
+        let data = Box::into_raw(Box::new(ModuleData {
+                                              module: module,
+                                              aux: aux,
+                                          }));
+        let r = unsafe {
+            ffi::sqlite3_create_module_v2(self.db(),
+                                          c_name.as_ptr(),
+                                          &(*data).module.base,
+                                          data as *mut c_void,
+                                          Some(free_boxed_value::<ModuleData<T>>))
+        };
+        self.decode_result(r)
+    }
+}
+
+unsafe extern "C" fn free_boxed_value<T>(p: *mut c_void) {
+    let _: Box<T> = Box::from_raw(p as *mut T);
+}
+
+// The `sqlite3_vtab` (resp. `sqlite3_vtab_cursor`) handed to SQLite must be
+// the first field of the allocation, so user types are wrapped in these.
+#[repr(C)]
+struct VTabWrapper<T> {
+    base: ffi::sqlite3_vtab,
+    vtab: T,
+}
+
+#[repr(C)]
+struct CursorWrapper<C> {
+    base: ffi::sqlite3_vtab_cursor,
+    cursor: C,
+}
+
+unsafe extern "C" fn rust_create<T>(db: *mut ffi::sqlite3,
+                                    aux: *mut c_void,
+                                    argc: c_int,
+                                    argv: *const *const c_char,
+                                    pp_vtab: *mut *mut ffi::sqlite3_vtab,
+                                    err_msg: *mut *mut c_char)
+                                    -> c_int
+    where T: CreateVTab
+{
+    vtab_init(db, aux, argc, argv, pp_vtab, err_msg, T::create)
+}
+
+unsafe extern "C" fn rust_connect<T>(db: *mut ffi::sqlite3,
+                                     aux: *mut c_void,
+                                     argc: c_int,
+                                     argv: *const *const c_char,
+                                     pp_vtab: *mut *mut ffi::sqlite3_vtab,
+                                     err_msg: *mut *mut c_char)
+                                     -> c_int
+    where T: VTab
+{
+    vtab_init(db, aux, argc, argv, pp_vtab, err_msg, T::connect)
+}
+
+unsafe fn vtab_init<T>(db: *mut ffi::sqlite3,
+                       aux: *mut c_void,
+                       argc: c_int,
+                       argv: *const *const c_char,
+                       pp_vtab: *mut *mut ffi::sqlite3_vtab,
+                       err_msg: *mut *mut c_char,
+                       init: fn(Option<&T::Aux>, &[&[u8]]) -> Result<(String, T)>)
+                       -> c_int
+    where T: VTab
+{
+    let data = &*(aux as *const ModuleData<T>);
+    let args = slice::from_raw_parts(argv, argc as usize);
+    let vec = args.iter()
+        .map(|&cs| CStr::from_ptr(cs).to_bytes())
+        .collect::<Vec<_>>();
+    let (sql, vtab) = match catch_panic(|| init(data.aux.as_ref(), &vec[..])) {
+        Ok(r) => r,
+        Err(err) => {
+            *err_msg = alloc(&err.to_string());
+            return error_code(&err);
+        }
+    };
+    let c_sql = match str_to_cstring(&sql) {
+        Ok(c_sql) => c_sql,
+        Err(err) => {
+            *err_msg = alloc(&err.to_string());
+            return ffi::SQLITE_ERROR;
+        }
+    };
+    let rc = ffi::sqlite3_declare_vtab(db, c_sql.as_ptr());
+    if rc == ffi::SQLITE_OK {
+        let boxed_vtab = Box::new(VTabWrapper {
+                                      base: mem::zeroed(),
+                                      vtab: vtab,
+                                  });
+        *pp_vtab = Box::into_raw(boxed_vtab) as *mut ffi::sqlite3_vtab;
+    } else {
+        // The reason why the schema was rejected is in the connection error message.
+        let err = error_from_handle(db, rc);
+        *err_msg = alloc(&err.to_string());
+    }
+    rc
+}
+
+unsafe extern "C" fn rust_best_index<T>(vtab: *mut ffi::sqlite3_vtab,
+                                        info: *mut ffi::sqlite3_index_info)
+                                        -> c_int
+    where T: VTab
+{
+    let vt = vtab as *mut VTabWrapper<T>;
+    let mut idx_info = IndexInfo(info);
+    let r = catch_panic(|| (*vt).vtab.best_index(&mut idx_info));
+    vtab_result(vtab, r)
+}
+
+unsafe extern "C" fn rust_disconnect<T>(vtab: *mut ffi::sqlite3_vtab) -> c_int
+    where T: VTab
+{
+    if vtab.is_null() {
+        return ffi::SQLITE_OK;
+    }
+    let boxed_vtab: Box<VTabWrapper<T>> = Box::from_raw(vtab as *mut VTabWrapper<T>);
+    let _ = catch_unwind(AssertUnwindSafe(|| drop(boxed_vtab)));
+    ffi::SQLITE_OK
+}
+
+unsafe extern "C" fn rust_destroy<T>(vtab: *mut ffi::sqlite3_vtab) -> c_int
+    where T: CreateVTab
+{
+    if vtab.is_null() {
+        return ffi::SQLITE_OK;
+    }
+    let vt = vtab as *mut VTabWrapper<T>;
+    match catch_panic(|| (*vt).vtab.destroy()) {
+        Ok(_) => {
+            let boxed_vtab: Box<VTabWrapper<T>> = Box::from_raw(vt);
+            let _ = catch_unwind(AssertUnwindSafe(|| drop(boxed_vtab)));
+            ffi::SQLITE_OK
+        }
+        Err(err) => vtab_result::<()>(vtab, Err(err)),
+    }
+}
+
+unsafe extern "C" fn rust_open<T>(vtab: *mut ffi::sqlite3_vtab,
+                                  pp_cursor: *mut *mut ffi::sqlite3_vtab_cursor)
+                                  -> c_int
+    where T: VTab
+{
+    let vt = vtab as *mut VTabWrapper<T>;
+    match catch_panic(|| (*vt).vtab.open()) {
+        Ok(cursor) => {
+            let boxed_cursor = Box::new(CursorWrapper {
+                                            base: mem::zeroed(),
+                                            cursor: cursor,
+                                        });
+            *pp_cursor = Box::into_raw(boxed_cursor) as *mut ffi::sqlite3_vtab_cursor;
+            ffi::SQLITE_OK
+        }
+        Err(err) => vtab_result::<()>(vtab, Err(err)),
+    }
+}
+
+unsafe extern "C" fn rust_close<C>(cursor: *mut ffi::sqlite3_vtab_cursor) -> c_int
+    where C: VTabCursor
+{
+    let boxed_cursor: Box<CursorWrapper<C>> = Box::from_raw(cursor as *mut CursorWrapper<C>);
+    let _ = catch_unwind(AssertUnwindSafe(|| drop(boxed_cursor)));
+    ffi::SQLITE_OK
+}
+
+unsafe extern "C" fn rust_filter<C>(cursor: *mut ffi::sqlite3_vtab_cursor,
+                                    idx_num: c_int,
+                                    _idx_str: *const c_char,
+                                    argc: c_int,
+                                    argv: *mut *mut ffi::sqlite3_value)
+                                    -> c_int
+    where C: VTabCursor
+{
+    let args = if argc == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(argv, argc as usize)
+    };
+    let values = Values { args: args };
+    let cr = cursor as *mut CursorWrapper<C>;
+    let r = catch_panic(|| (*cr).cursor.filter(idx_num, &values));
+    cursor_result(cursor, r)
+}
+
+unsafe extern "C" fn rust_next<C>(cursor: *mut ffi::sqlite3_vtab_cursor) -> c_int
+    where C: VTabCursor
+{
+    let cr = cursor as *mut CursorWrapper<C>;
+    let r = catch_panic(|| (*cr).cursor.next());
+    cursor_result(cursor, r)
+}
+
+unsafe extern "C" fn rust_eof<C>(cursor: *mut ffi::sqlite3_vtab_cursor) -> c_int
+    where C: VTabCursor
+{
+    let cr = cursor as *mut CursorWrapper<C>;
+    // There is no way to report an error here, so a panic ends the scan.
+    catch_unwind(AssertUnwindSafe(|| (*cr).cursor.eof())).unwrap_or(true) as c_int
+}
+
+unsafe extern "C" fn rust_column<C>(cursor: *mut ffi::sqlite3_vtab_cursor,
+                                    ctx: *mut ffi::sqlite3_context,
+                                    i: c_int)
+                                    -> c_int
+    where C: VTabCursor
+{
+    let cr = cursor as *mut CursorWrapper<C>;
+    let mut ctxt = Context(ctx);
+    let r = catch_panic(|| (*cr).cursor.column(&mut ctxt, i));
+    cursor_result(cursor, r)
+}
+
+unsafe extern "C" fn rust_rowid<C>(cursor: *mut ffi::sqlite3_vtab_cursor,
+                                   p_rowid: *mut ffi::sqlite3_int64)
+                                   -> c_int
+    where C: VTabCursor
+{
+    let cr = cursor as *mut CursorWrapper<C>;
+    match catch_panic(|| (*cr).cursor.rowid()) {
+        Ok(rowid) => {
+            *p_rowid = rowid;
+            ffi::SQLITE_OK
+        }
+        err => cursor_result(cursor, err),
+    }
+}
+
+/// Run a method of the virtual table or cursor, turning a panic into an error
+/// instead of unwinding into SQLite.
+fn catch_panic<T, F: FnOnce() -> Result<T>>(f: F) -> Result<T> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(r) => r,
+        Err(payload) => {
+            let msg = if let Some(s) = payload.downcast_ref::<&str>() {
+                s.to_string()
+            } else if let Some(s) = payload.downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "unknown panic".to_owned()
+            };
+            Err(Error::ModuleError(format!("virtual table method panicked: {}", msg)))
+        }
+    }
+}
+
+/// Virtual table cursors can set an error message by assigning a string to `zErrMsg`.
+unsafe fn cursor_result<T>(cursor: *mut ffi::sqlite3_vtab_cursor, result: Result<T>) -> c_int {
+    vtab_result((*cursor).pVtab, result)
+}
+
+/// Virtual tables methods can set an error message by assigning a string to `zErrMsg`.
+unsafe fn vtab_result<T>(vtab: *mut ffi::sqlite3_vtab, result: Result<T>) -> c_int {
+    match result {
+        Ok(_) => ffi::SQLITE_OK,
+        Err(err) => {
+            set_err_msg(vtab, &err);
+            error_code(&err)
+        }
+    }
+}
+
+unsafe fn set_err_msg(vtab: *mut ffi::sqlite3_vtab, err: &Error) {
+    let msg = match *err {
+        Error::SqliteFailure(_, Some(ref msg)) => msg.clone(),
+        Error::SqliteFailure(_, None) => return,
+        _ => err.to_string(),
+    };
+    if !(*vtab).zErrMsg.is_null() {
+        ffi::sqlite3_free((*vtab).zErrMsg as *mut c_void);
+    }
+    (*vtab).zErrMsg = alloc(&msg);
+}
+
+fn error_code(err: &Error) -> c_int {
+    match *err {
+        Error::SqliteFailure(ref err, _) => err.extended_code,
+        _ => ffi::SQLITE_ERROR,
+    }
+}
+
+// Space to hold this string must be obtained from an SQLite memory allocation
+// function, since SQLite frees it with `sqlite3_free`.
+unsafe fn alloc(s: &str) -> *mut c_char {
+    // Interior nul bytes would truncate the message; that's fine.
+    let len = s.len();
+    let p = ffi::sqlite3_malloc(len as c_int + 1) as *mut c_char;
+    if p.is_null() {
+        return ptr::null_mut();
+    }
+    ptr::copy_nonoverlapping(s.as_ptr() as *const c_char, p, len);
+    *p.offset(len as isize) = 0;
+    p
+}
+
+/// Escape double-quote (`"`) character occurences by doubling them (`""`).
+#[cfg(feature = "csvtab")]
+fn escape_double_quote(identifier: &str) -> Cow<str> {
+    if identifier.contains('"') {
+        // escape quote by doubling them
+        Owned(identifier.replace("\"", "\"\""))
+    } else {
+        Borrowed(identifier)
+    }
+}
+
+/// Dequote string
+#[cfg(feature = "csvtab")]
+fn dequote(s: &str) -> &str {
+    if s.len() < 2 {
+        return s;
+    }
+    match s.bytes().next() {
+        Some(b) if b == b'"' || b == b'\'' => match s.bytes().rev().next() {
+            Some(e) if e == b => &s[1..s.len() - 1],
+            _ => s,
+        },
+        _ => s,
+    }
+}
+
+/// The boolean can be one of:
+/// ```text
+/// 1 yes true on
+/// 0 no false off
+/// ```
+#[cfg(feature = "csvtab")]
+fn parse_boolean(s: &str) -> Option<bool> {
+    match s.to_lowercase().as_str() {
+        "yes" | "on" | "true" | "1" => Some(true),
+        "no" | "off" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::os::raw::c_int;
+
+    use vtab::{self, Context, CreateVTab, IndexConstraintOp, IndexInfo, VTab, VTabCursor,
+               Values};
+    use {Connection, Error, Result};
+
+    /// An eponymous-only table with a single row.
+    struct DummyTab;
+
+    impl VTab for DummyTab {
+        type Aux = ();
+        type Cursor = DummyTabCursor;
+
+        fn connect(_: Option<&()>, _: &[&[u8]]) -> Result<(String, DummyTab)> {
+            Ok(("CREATE TABLE x(value)".to_owned(), DummyTab))
+        }
+
+        fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
+            info.set_estimated_cost(1.);
+            Ok(())
+        }
+
+        fn open(&self) -> Result<DummyTabCursor> {
+            Ok(DummyTabCursor { row_id: 0 })
+        }
+    }
+
+    struct DummyTabCursor {
+        row_id: i64,
+    }
+
+    impl VTabCursor for DummyTabCursor {
+        fn filter(&mut self, _idx_num: c_int, _args: &Values) -> Result<()> {
+            self.row_id = 1;
+            Ok(())
+        }
+
+        fn next(&mut self) -> Result<()> {
+            self.row_id += 1;
+            Ok(())
+        }
+
+        fn eof(&self) -> bool {
+            self.row_id > 1
+        }
+
+        fn column(&self, ctx: &mut Context, _: c_int) -> Result<()> {
+            ctx.set_result(&self.row_id)
+        }
+
+        fn rowid(&self) -> Result<i64> {
+            Ok(self.row_id)
+        }
+    }
+
+    #[test]
+    fn test_dummy_module() {
+        let db = Connection::open_in_memory().unwrap();
+        db.create_module("dummy", vtab::eponymous_only_module::<DummyTab>(), None)
+            .unwrap();
+
+        let version = unsafe { ::ffi::sqlite3_libversion_number() };
+        if version < 3_009_000 {
+            return;
+        }
+
+        let mut s = db.prepare("SELECT * FROM dummy()").unwrap();
+        let dummy = s.query_row(&[], |row| row.get::<_, i32>(0)).unwrap();
+        assert_eq!(1, dummy);
+
+        assert!(db.execute_batch("CREATE VIRTUAL TABLE foo USING dummy()").is_err());
+    }
+
+    /// An eponymous-only table panicking in the method named by its aux data.
+    struct PanicTab {
+        method: &'static str,
+    }
+
+    impl VTab for PanicTab {
+        type Aux = &'static str;
+        type Cursor = PanicTabCursor;
+
+        fn connect(aux: Option<&&'static str>, _: &[&[u8]]) -> Result<(String, PanicTab)> {
+            Ok(("CREATE TABLE x(value)".to_owned(), PanicTab { method: *aux.unwrap() }))
+        }
+
+        fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
+            if self.method == "best_index" {
+                panic!("best_index failed");
+            }
+            info.set_estimated_cost(1.);
+            Ok(())
+        }
+
+        fn open(&self) -> Result<PanicTabCursor> {
+            Ok(PanicTabCursor {
+                   method: self.method,
+                   row_id: 0,
+               })
+        }
+    }
+
+    struct PanicTabCursor {
+        method: &'static str,
+        row_id: i64,
+    }
+
+    impl PanicTabCursor {
+        fn check(&self, method: &str) {
+            if self.method == method {
+                panic!("{} failed", method);
+            }
+        }
+    }
+
+    impl VTabCursor for PanicTabCursor {
+        fn filter(&mut self, _idx_num: c_int, _args: &Values) -> Result<()> {
+            self.check("filter");
+            self.row_id = 1;
+            Ok(())
+        }
+
+        fn next(&mut self) -> Result<()> {
+            self.check("next");
+            self.row_id += 1;
+            Ok(())
+        }
+
+        fn eof(&self) -> bool {
+            self.row_id > 2
+        }
+
+        fn column(&self, ctx: &mut Context, _: c_int) -> Result<()> {
+            self.check("column");
+            ctx.set_result(&self.row_id)
+        }
+
+        fn rowid(&self) -> Result<i64> {
+            self.check("rowid");
+            Ok(self.row_id)
+        }
+    }
+
+    /// A table declaring an invalid schema.
+    struct BadSchemaTab;
+
+    impl VTab for BadSchemaTab {
+        type Aux = ();
+        type Cursor = DummyTabCursor;
+
+        fn connect(_: Option<&()>, _: &[&[u8]]) -> Result<(String, BadSchemaTab)> {
+            Ok(("CREATE TABLE x(value, value)".to_owned(), BadSchemaTab))
+        }
+
+        fn best_index(&self, _: &mut IndexInfo) -> Result<()> {
+            Ok(())
+        }
+
+        fn open(&self) -> Result<DummyTabCursor> {
+            Ok(DummyTabCursor { row_id: 0 })
+        }
+    }
+
+    impl CreateVTab for BadSchemaTab {}
+
+    #[test]
+    fn test_invalid_schema() {
+        let db = Connection::open_in_memory().unwrap();
+        db.create_module("bad", vtab::read_only_module::<BadSchemaTab>(), None)
+            .unwrap();
+        match db.execute_batch("CREATE VIRTUAL TABLE temp.t USING bad") {
+            Err(Error::SqliteFailure(_, Some(msg))) => {
+                assert!(msg.contains("duplicate column name"), msg)
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_panicking_cursor() {
+        let version = unsafe { ::ffi::sqlite3_libversion_number() };
+        if version < 3_009_000 {
+            return;
+        }
+
+        let db = Connection::open_in_memory().unwrap();
+        for method in &["best_index", "filter", "next", "column", "rowid"] {
+            db.create_module(method, vtab::eponymous_only_module::<PanicTab>(), Some(method))
+                .unwrap();
+            let sql = format!("SELECT SUM(rowid + value) FROM {}", method);
+            match db.query_row(&sql, &[], |row| row.get::<_, i64>(0)) {
+                Err(Error::SqliteFailure(_, Some(msg))) => {
+                    assert!(msg.contains(&format!("panicked: {} failed", method)), msg)
+                }
+                r => panic!("Unexpected result {:?} for {}", r, method),
+            }
+        }
+
+        // The connection is still usable.
+        db.create_module("ok", vtab::eponymous_only_module::<PanicTab>(), Some("none"))
+            .unwrap();
+        let sum: i64 = db.query_row("SELECT SUM(rowid + value) FROM ok", &[], |row| row.get(0))
+            .unwrap();
+        assert_eq!(6, sum);
+    }
+
+    /// A table over the values given as aux data, supporting `rowid = ?` lookups.
+    struct ValuesTab {
+        values: Vec<String>,
+    }
+
+    impl VTab for ValuesTab {
+        type Aux = Vec<String>;
+        type Cursor = ValuesTabCursor;
+
+        fn connect(aux: Option<&Vec<String>>, args: &[&[u8]]) -> Result<(String, ValuesTab)> {
+            if args.len() > 3 {
+                return Err(Error::ModuleError("no arguments expected".to_owned()));
+            }
+            let vtab = ValuesTab { values: aux.cloned().unwrap_or_default() };
+            Ok(("CREATE TABLE x(value TEXT)".to_owned(), vtab))
+        }
+
+        fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
+            let mut rowid_constraint = None;
+            for (i, constraint) in info.constraints().enumerate() {
+                if constraint.is_usable() && constraint.column() == -1 &&
+                   constraint.operator() == IndexConstraintOp::Eq {
+                    rowid_constraint = Some(i);
+                }
+            }
+            if let Some(i) = rowid_constraint {
+                let mut usage = info.constraint_usage(i);
+                usage.set_argv_index(1);
+                usage.set_omit(true);
+                info.set_idx_num(1);
+                info.set_estimated_cost(1.);
+            } else {
+                info.set_idx_num(0);
+                info.set_estimated_cost(self.values.len() as f64);
+            }
+            Ok(())
+        }
+
+        fn open(&self) -> Result<ValuesTabCursor> {
+            Ok(ValuesTabCursor {
+                   values: self.values.clone(),
+                   row_id: 0,
+                   last: 0,
+               })
+        }
+    }
+
+    impl CreateVTab for ValuesTab {}
+
+    struct ValuesTabCursor {
+        values: Vec<String>,
+        row_id: i64,
+        last: i64,
+    }
+
+    impl VTabCursor for ValuesTabCursor {
+        fn filter(&mut self, idx_num: c_int, args: &Values) -> Result<()> {
+            if idx_num == 1 {
+                let row_id: i64 = try!(args.get(0));
+                self.row_id = row_id;
+                self.last = row_id;
+            } else {
+                self.row_id = 1;
+                self.last = self.values.len() as i64;
+            }
+            Ok(())
+        }
+
+        fn next(&mut self) -> Result<()> {
+            self.row_id += 1;
+            Ok(())
+        }
+
+        fn eof(&self) -> bool {
+            self.row_id < 1 || self.row_id > self.last || self.row_id > self.values.len() as i64
+        }
+
+        fn column(&self, ctx: &mut Context, _: c_int) -> Result<()> {
+            ctx.set_result(&self.values[self.row_id as usize - 1])
+        }
+
+        fn rowid(&self) -> Result<i64> {
+            Ok(self.row_id)
+        }
+    }
+
+    #[test]
+    fn test_create_virtual_table() {
+        let db = Connection::open_in_memory().unwrap();
+        let values = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+        db.create_module("vals", vtab::read_only_module::<ValuesTab>(), Some(values))
+            .unwrap();
+
+        db.execute_batch("CREATE VIRTUAL TABLE temp.vals USING vals").unwrap();
+
+        let mut s = db.prepare("SELECT rowid, value FROM vals").unwrap();
+        let rows: Vec<(i64, String)> = s.query_map(&[], |row| (row.get(0), row.get(1)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows,
+                   vec![(1, "a".to_owned()), (2, "b".to_owned()), (3, "c".to_owned())]);
+
+        let value: String = db.query_row("SELECT value FROM vals WHERE rowid = 2",
+                                         &[],
+                                         |row| row.get(0))
+            .unwrap();
+        assert_eq!("b", value);
+
+        let count: i64 = db.query_row("SELECT COUNT(*) FROM vals WHERE rowid = 42",
+                                      &[],
+                                      |row| row.get(0))
+            .unwrap();
+        assert_eq!(0, count);
+
+        // Errors from `connect` are reported by SQLite.
+        match db.execute_batch("CREATE VIRTUAL TABLE temp.bad USING vals(1)") {
+            Err(Error::SqliteFailure(_, Some(msg))) => assert!(msg.contains("no arguments")),
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        db.execute_batch("DROP TABLE vals").unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "csvtab")]
+    fn test_dequote() {
+        assert_eq!("", super::dequote(""));
+        assert_eq!("'", super::dequote("'"));
+        assert_eq!("\"", super::dequote("\""));
+        assert_eq!("'\"", super::dequote("'\""));
+        assert_eq!("", super::dequote("''"));
+        assert_eq!("", super::dequote("\"\""));
+        assert_eq!("x", super::dequote("'x'"));
+        assert_eq!("x", super::dequote("\"x\""));
+        assert_eq!("x", super::dequote("x"));
+    }
+
+    #[test]
+    #[cfg(feature = "csvtab")]
+    fn test_parse_boolean() {
+        assert_eq!(None, super::parse_boolean(""));
+        assert_eq!(Some(true), super::parse_boolean("1"));
+        assert_eq!(Some(true), super::parse_boolean("yes"));
+        assert_eq!(Some(true), super::parse_boolean("on"));
+        assert_eq!(Some(true), super::parse_boolean("true"));
+        assert_eq!(Some(false), super::parse_boolean("0"));
+        assert_eq!(Some(false), super::parse_boolean("no"));
+        assert_eq!(Some(false), super::parse_boolean("off"));
+        assert_eq!(Some(false), super::parse_boolean("false"));
+    }
+}
@@ -0,0 +1,311 @@
+//! generate series virtual table.
+//!
+//! Port of C [generate series "function"](http://www.sqlite.org/cgi/src/finfo?name=ext/misc/series.c).
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use rusqlite::{Connection, Result};
+//! # use rusqlite::vtab::series;
+//! fn example(db: &Connection) -> Result<()> {
+//!     // Note: This should be done once (usually when opening the DB).
+//!     try!(series::load_module(&db));
+//!     // One row per day of January 2018, including days without any sale.
+//!     let mut stmt = try!(db.prepare("SELECT date('2018-01-01', '+' || value || ' days') AS day \
+//!                                     FROM generate_series(0, 30)"));
+//!     let days = try!(stmt.query_map(&[], |row| row.get::<_, String>(0)));
+//!     for day in days {
+//!         println!("{}", try!(day));
+//!     }
+//!     Ok(())
+//! }
+//! ```
+use std::default::Default;
+use std::os::raw::c_int;
+
+use ffi;
+use vtab::{eponymous_only_module, Context, IndexConstraintOp, IndexInfo, VTab, VTabCursor, Values};
+use {Connection, Error, Result};
+
+/// Register the "generate_series" module.
+///
+/// ```sql
+/// SELECT value FROM generate_series(start [, stop [, step]]);
+/// ```
+///
+/// `stop` defaults to 4294967295 and `step` to 1. A negative `step` yields the
+/// series in descending order.
+pub fn load_module(conn: &Connection) -> Result<()> {
+    let aux: Option<()> = None;
+    conn.create_module("generate_series", eponymous_only_module::<SeriesTab>(), aux)
+}
+
+// Column numbers
+// const SERIES_COLUMN_VALUE : c_int = 0;
+const SERIES_COLUMN_START: c_int = 1;
+const SERIES_COLUMN_STOP: c_int = 2;
+const SERIES_COLUMN_STEP: c_int = 3;
+
+bitflags! {
+    #[repr(C)]
+    struct QueryPlanFlags: ::std::os::raw::c_int {
+        // start = $value  -- constraint exists
+        const START = 1;
+        // stop = $value   -- constraint exists
+        const STOP  = 2;
+        // step = $value   -- constraint exists
+        const STEP  = 4;
+        // output in descending order
+        const DESC  = 8;
+        // output in ascending order
+        const ASC   = 16;
+        // Both start and stop
+        const BOTH  = QueryPlanFlags::START.bits | QueryPlanFlags::STOP.bits;
+    }
+}
+
+/// An instance of the Series virtual table
+struct SeriesTab;
+
+impl VTab for SeriesTab {
+    type Aux = ();
+    type Cursor = SeriesTabCursor;
+
+    fn connect(_: Option<&()>, _args: &[&[u8]]) -> Result<(String, SeriesTab)> {
+        Ok(("CREATE TABLE x(value,start hidden,stop hidden,step hidden)".to_owned(), SeriesTab))
+    }
+
+    fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
+        // The query plan bitmask
+        let mut idx_num: QueryPlanFlags = QueryPlanFlags::empty();
+        // Mask of unusable constraints
+        let mut unusable_mask: QueryPlanFlags = QueryPlanFlags::empty();
+        // Constraints on start, stop, and step
+        let mut a_idx: [Option<usize>; 3] = [None, None, None];
+        for (i, constraint) in info.constraints().enumerate() {
+            let (i_col, i_mask) = match constraint.column() {
+                SERIES_COLUMN_START => (0, QueryPlanFlags::START),
+                SERIES_COLUMN_STOP => (1, QueryPlanFlags::STOP),
+                SERIES_COLUMN_STEP => (2, QueryPlanFlags::STEP),
+                _ => continue,
+            };
+            if !constraint.is_usable() {
+                unusable_mask |= i_mask;
+            } else if constraint.operator() == IndexConstraintOp::Eq {
+                idx_num |= i_mask;
+                a_idx[i_col] = Some(i);
+            }
+        }
+
+        let mut num_of_arg = 0;
+        for j in a_idx.iter().filter_map(|j| *j) {
+            num_of_arg += 1;
+            let mut constraint_usage = info.constraint_usage(j);
+            constraint_usage.set_argv_index(num_of_arg);
+            constraint_usage.set_omit(true);
+        }
+
+        if !(unusable_mask & !idx_num).is_empty() {
+            // The start, stop, and step columns are inputs. Therefore if there
+            // are unusable constraints on any of start, stop, or step then
+            // this plan is unusable.
+            return Err(Error::SqliteFailure(ffi::Error::new(ffi::SQLITE_CONSTRAINT), None));
+        }
+
+        if idx_num.contains(QueryPlanFlags::BOTH) {
+            // Both start= and stop= boundaries are available.
+            info.set_estimated_cost(f64::from(2 - if idx_num.contains(QueryPlanFlags::STEP) {
+                                                      1
+                                                  } else {
+                                                      0
+                                                  }));
+            // Only an ORDER BY on the "value" column alone can be consumed.
+            let mut order_by = None;
+            if info.num_of_order_by() == 1 {
+                if let Some(o) = info.order_bys().next() {
+                    if o.column() == 0 {
+                        order_by = Some(o.is_order_by_desc());
+                    }
+                }
+            }
+            if let Some(desc) = order_by {
+                idx_num |= if desc {
+                    QueryPlanFlags::DESC
+                } else {
+                    QueryPlanFlags::ASC
+                };
+                info.set_order_by_consumed(true);
+            }
+        } else {
+            // If either boundary is missing, we have to generate a huge span
+            // of numbers. Make this case very expensive so that the query
+            // planner will work hard to avoid it.
+            info.set_estimated_cost(2_147_483_647f64);
+        }
+        info.set_idx_num(idx_num.bits());
+        Ok(())
+    }
+
+    fn open(&self) -> Result<SeriesTabCursor> {
+        Ok(SeriesTabCursor::new())
+    }
+}
+
+/// A cursor for the Series virtual table
+#[derive(Default)]
+struct SeriesTabCursor {
+    /// True to count down rather than up
+    is_desc: bool,
+    /// The rowid
+    row_id: i64,
+    /// Current value ("value")
+    value: i64,
+    /// Mimimum value ("start")
+    min_value: i64,
+    /// Maximum value ("stop")
+    max_value: i64,
+    /// Increment ("step")
+    step: i64,
+    /// True once `value` would overflow
+    overflow: bool,
+}
+
+impl SeriesTabCursor {
+    fn new() -> SeriesTabCursor {
+        SeriesTabCursor::default()
+    }
+}
+
+impl VTabCursor for SeriesTabCursor {
+    fn filter(&mut self, idx_num: c_int, args: &Values) -> Result<()> {
+        let idx_num = QueryPlanFlags::from_bits_truncate(idx_num);
+        let mut i = 0;
+        if idx_num.contains(QueryPlanFlags::START) {
+            self.min_value = try!(args.get(i));
+            i += 1;
+        } else {
+            self.min_value = 0;
+        }
+        if idx_num.contains(QueryPlanFlags::STOP) {
+            self.max_value = try!(args.get(i));
+            i += 1;
+        } else {
+            self.max_value = 0xffff_ffff;
+        }
+        self.is_desc = false;
+        if idx_num.contains(QueryPlanFlags::STEP) {
+            self.step = try!(args.get(i));
+            if self.step == 0 {
+                self.step = 1;
+            } else if self.step < 0 {
+                self.step = self.step.checked_neg().unwrap_or(::std::i64::MAX);
+                self.is_desc = !idx_num.contains(QueryPlanFlags::ASC);
+            }
+        } else {
+            self.step = 1;
+        }
+        if idx_num.contains(QueryPlanFlags::DESC) {
+            self.is_desc = true;
+        }
+        if self.is_desc {
+            self.value = self.max_value;
+            if self.step > 0 && self.max_value > self.min_value {
+                // Start on the last value of the ascending series.
+                let span = self.max_value.wrapping_sub(self.min_value) as u64;
+                self.value -= (span % self.step as u64) as i64;
+            }
+        } else {
+            self.value = self.min_value;
+        }
+        self.overflow = false;
+        self.row_id = 1;
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<()> {
+        // Stop (rather than overflow) at the ends of the `i64` range.
+        let value = if self.is_desc {
+            self.value.checked_sub(self.step)
+        } else {
+            self.value.checked_add(self.step)
+        };
+        match value {
+            Some(value) => self.value = value,
+            None => self.overflow = true,
+        }
+        self.row_id += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        if self.overflow || self.min_value > self.max_value {
+            true
+        } else if self.is_desc {
+            self.value < self.min_value
+        } else {
+            self.value > self.max_value
+        }
+    }
+
+    fn column(&self, ctx: &mut Context, i: c_int) -> Result<()> {
+        let x = match i {
+            SERIES_COLUMN_START => self.min_value,
+            SERIES_COLUMN_STOP => self.max_value,
+            SERIES_COLUMN_STEP => self.step,
+            _ => self.value,
+        };
+        ctx.set_result(&x)
+    }
+
+    fn rowid(&self) -> Result<i64> {
+        Ok(self.row_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ffi;
+    use vtab::series;
+    use Connection;
+
+    fn series(db: &Connection, sql: &str) -> Vec<i64> {
+        let mut s = db.prepare(sql).unwrap();
+        let series = s.query_map(&[], |row| row.get::<_, i64>(0)).unwrap();
+        series.map(|v| v.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_series_module() {
+        let version = unsafe { ffi::sqlite3_libversion_number() };
+        if version < 3_009_000 {
+            return;
+        }
+
+        let db = Connection::open_in_memory().unwrap();
+        series::load_module(&db).unwrap();
+
+        assert_eq!(vec![0, 5, 10, 15, 20],
+                   series(&db, "SELECT * FROM generate_series(0,20,5)"));
+        assert_eq!(vec![20, 15, 10, 5, 0],
+                   series(&db, "SELECT * FROM generate_series(0,20,5) ORDER BY value DESC"));
+        assert_eq!(vec![18, 13, 8, 3],
+                   series(&db, "SELECT value FROM generate_series(3,20,-5)"));
+        assert_eq!(vec![3, 8, 13, 18],
+                   series(&db, "SELECT value FROM generate_series(3,20,-5) ORDER BY value"));
+        assert_eq!(vec![1, 2, 3], series(&db, "SELECT value FROM generate_series(1,3)"));
+        assert!(series(&db, "SELECT value FROM generate_series(3,1)").is_empty());
+        assert_eq!(vec![9223372036854775806, 9223372036854775807],
+                   series(&db,
+                          "SELECT value FROM generate_series(9223372036854775806, \
+                           9223372036854775807)"));
+
+        let days: Vec<String> = {
+            let mut s = db.prepare("SELECT date('2018-02-27', '+' || value || ' days') \
+                                    FROM generate_series(0, 2)")
+                .unwrap();
+            let days = s.query_map(&[], |row| row.get(0)).unwrap();
+            days.map(|d| d.unwrap()).collect()
+        };
+        assert_eq!(days, vec!["2018-02-27", "2018-02-28", "2018-03-01"]);
+    }
+}
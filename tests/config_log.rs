@@ -4,6 +4,8 @@
 #[cfg(feature = "trace")]
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "log")]
+extern crate log;
 extern crate rusqlite;
 
 #[cfg(feature = "trace")]
@@ -31,6 +33,46 @@ fn main() {
     assert_eq!(logs_received.len(), 1);
     assert_eq!(logs_received[0].0, 10);
     assert_eq!(logs_received[0].1, "First message from rusqlite");
+
+    #[cfg(feature = "log")]
+    config_log_to_log_crate();
+}
+
+#[cfg(feature = "log")]
+fn config_log_to_log_crate() {
+    use log::{Level, Log, Metadata, Record};
+    use std::sync::Mutex;
+
+    use rusqlite::trace;
+
+    lazy_static! {
+        static ref LOGGED: Mutex<Vec<(Level, String)>> = Mutex::new(Vec::new());
+    }
+    struct Logger;
+    impl Log for Logger {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+        fn log(&self, record: &Record) {
+            LOGGED.lock().unwrap().push((record.level(), record.args().to_string()));
+        }
+        fn flush(&self) {}
+    }
+    static LOGGER: Logger = Logger;
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    unsafe { trace::config_log_to_log_crate() }.unwrap();
+    trace::log(27, "notice"); // SQLITE_NOTICE
+    trace::log(28 | (1 << 8), "warning"); // SQLITE_WARNING_AUTOINDEX
+    trace::log(14, "error"); // SQLITE_CANTOPEN
+    unsafe { trace::config_log(None) }.unwrap();
+
+    let logged = LOGGED.lock().unwrap();
+    assert_eq!(*logged,
+               vec![(Level::Info, "(27) notice".to_owned()),
+                    (Level::Warn, "(284) warning".to_owned()),
+                    (Level::Error, "(14) error".to_owned())]);
 }
 
 #[cfg(not(feature = "trace"))]